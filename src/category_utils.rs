@@ -0,0 +1,91 @@
+// category_utils.rs
+
+use crate::error_utils::is_not_found;
+use crate::TagCategoryConfig;
+use errors::SzurubooruClientError;
+use models::{CreateUpdateTagCategory, TagCategoryResource};
+use std::io::{Error, ErrorKind};
+use szurubooru_client::*;
+
+pub async fn list_categories(client: &SzurubooruClient) -> SzurubooruResult<Vec<TagCategoryResource>> {
+    let mut categories = client.request().list_tag_categories().await?.results;
+    categories.sort_by_key(|category| category.order);
+    Ok(categories)
+}
+
+pub async fn fetch_category(client: &SzurubooruClient, name: &str) -> SzurubooruResult<Option<TagCategoryResource>> {
+    match client.request().get_tag_category(name).await {
+        Ok(category) => Ok(Some(category)),
+        Err(e) if is_not_found(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn require_category(client: &SzurubooruClient, name: &str) -> SzurubooruResult<TagCategoryResource> {
+    fetch_category(client, name).await?.ok_or_else(|| {
+        SzurubooruClientError::IOError(Error::new(ErrorKind::NotFound, format!("Category {} does not exist.", name)))
+    })
+}
+
+pub fn format_category(category: &TagCategoryResource) -> String {
+    format!(
+        "{:<20} color: {:<10} order: {:<4} usages: {:<8}{}",
+        category.name.clone().unwrap_or_default(),
+        category.color.clone().unwrap_or_default(),
+        category.order.map(|order| order.to_string()).unwrap_or_default(),
+        category.usages.unwrap_or(0),
+        if category.default == Some(true) { " (default)" } else { "" },
+    )
+}
+
+/// Creates or updates the categories from the `[[tag_categories]]` config section.
+/// Categories on the server that are not listed are left alone.
+pub async fn apply_categories(
+    client: &SzurubooruClient,
+    categories: &[TagCategoryConfig],
+    dry_run: bool,
+) -> SzurubooruResult<usize> {
+    let mut changed = 0;
+
+    for wanted in categories {
+        let current = fetch_category(client, &wanted.name).await?;
+
+        let update = CreateUpdateTagCategory {
+            version: current.as_ref().map(|category| category.version),
+            name: Some(wanted.name.clone()),
+            color: wanted.color.clone(),
+            order: wanted.order,
+        };
+
+        match &current {
+            None => {
+                println!("+ {}", wanted.name);
+                if !dry_run {
+                    client.request().create_tag_category(&update).await?;
+                }
+                changed += 1;
+            }
+            Some(category)
+                if (wanted.color.is_some() && wanted.color != category.color)
+                    || (wanted.order.is_some() && wanted.order != category.order) =>
+            {
+                println!("~ {}", wanted.name);
+                if !dry_run {
+                    client.request().update_tag_category(&wanted.name, &update).await?;
+                }
+                changed += 1;
+            }
+            Some(_) => {}
+        }
+
+        let is_default = current.as_ref().and_then(|category| category.default) == Some(true);
+        if wanted.default && !is_default {
+            println!("* {} is now the default category", wanted.name);
+            if !dry_run {
+                client.request().set_default_tag_category(&wanted.name).await?;
+            }
+        }
+    }
+
+    Ok(changed)
+}
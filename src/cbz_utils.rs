@@ -0,0 +1,74 @@
+// cbz_utils.rs
+
+use errors::SzurubooruClientError;
+use std::fs;
+use std::io::{Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use szurubooru_client::*;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+pub struct ComicInfo {
+    pub title: String,
+    pub summary: String,
+    pub web: String,
+    pub tags: Vec<String>,
+}
+
+/// Packs `pages` in order into a CBZ archive with a ComicInfo.xml.
+/// Images are already compressed, so they are stored as-is.
+pub fn write_cbz(cbz_path: &Path, pages: &[PathBuf], info: &ComicInfo) -> SzurubooruResult<()> {
+    let zip_error = |e: zip::result::ZipError| SzurubooruClientError::IOError(Error::new(ErrorKind::Other, e.to_string()));
+
+    let file = fs::File::create(cbz_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for page in pages {
+        let name = page.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let content = fs::read(page).map_err(|e| SzurubooruClientError::IOError(e))?;
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(&content).map_err(|e| SzurubooruClientError::IOError(e))?;
+    }
+
+    zip.start_file("ComicInfo.xml", options.compression_method(CompressionMethod::Deflated))
+        .map_err(zip_error)?;
+    zip.write_all(comic_info_xml(info, pages.len()).as_bytes())
+        .map_err(|e| SzurubooruClientError::IOError(e))?;
+    zip.finish().map_err(zip_error)?;
+
+    Ok(())
+}
+
+fn comic_info_xml(info: &ComicInfo, page_count: usize) -> String {
+    let pages: String = (0..page_count)
+        .map(|page| format!("    <Page Image=\"{}\" />\n", page))
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <Title>{}</Title>
+  <Summary>{}</Summary>
+  <Web>{}</Web>
+  <Tags>{}</Tags>
+  <PageCount>{}</PageCount>
+  <Pages>
+{}  </Pages>
+</ComicInfo>
+"#,
+        escape_xml(&info.title),
+        escape_xml(&info.summary),
+        escape_xml(&info.web),
+        escape_xml(&info.tags.join(",")),
+        page_count,
+        pages,
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
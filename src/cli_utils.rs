@@ -0,0 +1,75 @@
+// cli_utils.rs
+
+use std::collections::HashMap;
+use std::io;
+use szurubooru_client::tokens::QueryToken;
+
+/// Flags that never take a value, everything else starting with `--` consumes the next argument.
+const BOOL_FLAGS: &[&str] = &["--dry-run", "--alias", "--yes", "--keep-implications", "--no-alias", "--pools", "--cbz", "--preview", "--content"];
+
+pub struct Args {
+    pub positionals: Vec<String>,
+    flags: HashMap<String, Vec<Option<String>>>,
+}
+
+impl Args {
+    /// Splits the arguments into positionals and `--flag [value]` / `--flag=value` options.
+    /// A flag may be given more than once, every value is kept.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Args {
+        let mut positionals = Vec::new();
+        let mut flags: HashMap<String, Vec<Option<String>>> = HashMap::new();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                positionals.push(arg);
+            } else if let Some((name, value)) = arg.split_once('=') {
+                flags.entry(name.to_string()).or_default().push(Some(value.to_string()));
+            } else if BOOL_FLAGS.contains(&arg.as_str()) {
+                flags.entry(arg).or_default().push(None);
+            } else {
+                let value = args.next_if(|next| !next.starts_with("--"));
+                flags.entry(arg).or_default().push(value);
+            }
+        }
+
+        Args { positionals, flags }
+    }
+
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.flags.contains_key(name)
+    }
+
+    /// The last value given for `name`.
+    pub fn flag_value(&self, name: &str) -> Option<&str> {
+        self.flag_values(name).pop()
+    }
+
+    /// Every value given for `name`, in order.
+    pub fn flag_values(&self, name: &str) -> Vec<&str> {
+        self.flags.get(name).into_iter().flatten().filter_map(|value| value.as_deref()).collect()
+    }
+}
+
+/// Turns a szurubooru search string into query tokens, e.g. `tag_a category:meta sort:usages`.
+pub fn parse_query(query: &str) -> Vec<QueryToken> {
+    query
+        .split_whitespace()
+        .map(|part| match part.split_once(':') {
+            Some(("sort", value)) => QueryToken::sort(value),
+            Some(("special", value)) => QueryToken::special(value),
+            Some((key, value)) if !key.is_empty() => QueryToken::token(key, value),
+            _ => QueryToken::anonymous(part),
+        })
+        .collect()
+}
+
+/// Asks a yes/no question on stdin, accepting "yes" or "y" (case-insensitive).
+pub fn confirm(prompt: &str) -> io::Result<bool> {
+    println!("{} (yes/y/no)", prompt);
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(input.trim().eq_ignore_ascii_case("yes") || input.trim().eq_ignore_ascii_case("y"))
+}
@@ -0,0 +1,65 @@
+// csv_utils.rs
+
+use errors::SzurubooruClientError;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead};
+use szurubooru_client::*;
+
+/// Splits a single CSV line, honouring double quoted fields and `""` escapes.
+pub fn parse_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+pub fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Reads a comma separated file with a header line into one map per row, keyed by column name.
+pub fn read_csv_records(file_path: &str) -> SzurubooruResult<Vec<HashMap<String, String>>> {
+    let file = fs::File::open(file_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut lines = io::BufReader::new(file).lines();
+
+    let header = match lines.next() {
+        Some(line) => parse_csv_line(&line.map_err(|e| SzurubooruClientError::IOError(e))?, ','),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut records = Vec::new();
+    for line in lines {
+        let line = line.map_err(|e| SzurubooruClientError::IOError(e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = header
+            .iter()
+            .cloned()
+            .zip(parse_csv_line(&line, ','))
+            .collect();
+        records.push(record);
+    }
+
+    Ok(records)
+}
@@ -0,0 +1,109 @@
+// danbooru_utils.rs
+
+use crate::csv_utils::read_csv_records;
+use crate::tag_utils::Tag;
+use crate::DanbooruConfig;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use szurubooru_client::*;
+
+pub struct DumpTags {
+    pub tags: Vec<Tag>,
+    /// Aliases whose name is already used by a different local tag, these need `merge tag` instead.
+    pub conflicting_aliases: Vec<(String, String)>,
+}
+
+/// Builds tag definitions from `tags.csv`, `tag_aliases.csv` and `tag_implications.csv` in `folder`.
+///
+/// `local_names` maps every local name and alias (lowercase) to the primary name of its tag.
+/// Only tags that exist locally or have at least `min_usage` posts in the dump are kept.
+pub fn read_dump(
+    folder: &Path,
+    local_names: &HashMap<String, String>,
+    config: &DanbooruConfig,
+    min_usage: u32,
+) -> SzurubooruResult<DumpTags> {
+    let mut tags: HashMap<String, Tag> = HashMap::new();
+    let mut order = Vec::new();
+
+    for record in read_csv_records(&folder.join("tags.csv").to_string_lossy())? {
+        let Some(name) = record.get("name").map(|name| name.to_lowercase()) else {
+            continue;
+        };
+        let post_count: u32 = record
+            .get("post_count")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0);
+
+        if !local_names.contains_key(&name) && post_count < min_usage {
+            continue;
+        }
+
+        let category = record
+            .get("category")
+            .and_then(|category| config.categories.get(category))
+            .cloned()
+            .unwrap_or_default();
+
+        order.push(name.clone());
+        tags.insert(
+            name.clone(),
+            Tag {
+                name,
+                category,
+                aliases: Vec::new(),
+                implications: Vec::new(),
+                suggested: Vec::new(),
+                usages: post_count,
+            },
+        );
+    }
+
+    let mut conflicting_aliases = Vec::new();
+    for (antecedent, consequent) in read_relations(folder, "tag_aliases.csv")? {
+        let Some(tag) = tags.get_mut(&consequent) else {
+            continue;
+        };
+        let owner = local_names.get(&antecedent);
+        if owner.is_some() && owner != local_names.get(&consequent) {
+            conflicting_aliases.push((antecedent, consequent));
+            continue;
+        }
+        tag.aliases.push(antecedent);
+    }
+
+    let kept: HashSet<String> = tags.keys().cloned().collect();
+    for (antecedent, consequent) in read_relations(folder, "tag_implications.csv")? {
+        if !kept.contains(&consequent) {
+            continue;
+        }
+        if let Some(tag) = tags.get_mut(&antecedent) {
+            tag.implications.push(consequent);
+        }
+    }
+
+    let tags = order
+        .into_iter()
+        .filter_map(|name| tags.remove(&name))
+        .collect();
+
+    Ok(DumpTags { tags, conflicting_aliases })
+}
+
+/// Reads active `antecedent_name -> consequent_name` pairs, an absent file yields no pairs.
+fn read_relations(folder: &Path, file_name: &str) -> SzurubooruResult<Vec<(String, String)>> {
+    let path = folder.join(file_name);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    Ok(read_csv_records(&path.to_string_lossy())?
+        .into_iter()
+        .filter(|record| record.get("status").is_none_or(|status| status == "active"))
+        .filter_map(|record| {
+            let antecedent = record.get("antecedent_name")?.to_lowercase();
+            let consequent = record.get("consequent_name")?.to_lowercase();
+            Some((antecedent, consequent))
+        })
+        .collect())
+}
@@ -0,0 +1,105 @@
+// duplicate_utils.rs
+
+use models::PostResource;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use szurubooru_client::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeepRule {
+    LowestId,
+    HighestResolution,
+    MostTags,
+    LargestFile,
+}
+
+impl KeepRule {
+    pub fn parse(rule: &str) -> Option<KeepRule> {
+        match rule.trim() {
+            "lowest-id" => Some(KeepRule::LowestId),
+            "highest-resolution" => Some(KeepRule::HighestResolution),
+            "most-tags" => Some(KeepRule::MostTags),
+            "largest-file" => Some(KeepRule::LargestFile),
+            _ => None,
+        }
+    }
+
+    /// Orders the post to keep first.
+    fn compare(self, a: &PostResource, b: &PostResource) -> Ordering {
+        let pixels = |post: &PostResource| {
+            post.canvas_width.unwrap_or(0) as u64 * post.canvas_height.unwrap_or(0) as u64
+        };
+        let tags = |post: &PostResource| post.tags.as_ref().map_or(0, |tags| tags.len());
+        let file_size = |post: &PostResource| post.file_size.unwrap_or(0);
+
+        match self {
+            KeepRule::LowestId => a.id.cmp(&b.id),
+            KeepRule::HighestResolution => pixels(b).cmp(&pixels(a)),
+            KeepRule::MostTags => tags(b).cmp(&tags(a)),
+            KeepRule::LargestFile => file_size(b).cmp(&file_size(a)),
+        }
+    }
+}
+
+/// Parses a comma separated list of rules, later rules break ties of earlier ones.
+pub fn parse_keep_rules(rules: &str) -> Option<Vec<KeepRule>> {
+    rules.split(',').map(KeepRule::parse).collect()
+}
+
+/// Picks the post to keep by applying the rules in order, the lowest id wins any remaining tie.
+pub fn choose_keeper<'a>(posts: &[&'a PostResource], rules: &[KeepRule]) -> &'a PostResource {
+    posts
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            rules
+                .iter()
+                .fold(Ordering::Equal, |ordering, rule| ordering.then_with(|| rule.compare(a, b)))
+                .then_with(|| a.id.cmp(&b.id))
+        })
+        .expect("duplicate groups are never empty")
+}
+
+/// Groups posts connected by similarity, a post similar to two others joins them into one group.
+#[derive(Default)]
+pub struct DuplicateGroups {
+    parents: HashMap<u32, u32>,
+}
+
+impl DuplicateGroups {
+    fn root(&mut self, post: u32) -> u32 {
+        let parent = *self.parents.entry(post).or_insert(post);
+        if parent == post {
+            return post;
+        }
+        let root = self.root(parent);
+        self.parents.insert(post, root);
+        root
+    }
+
+    pub fn connect(&mut self, a: u32, b: u32) {
+        let (root_a, root_b) = (self.root(a), self.root(b));
+        if root_a != root_b {
+            self.parents.insert(root_a.max(root_b), root_a.min(root_b));
+        }
+    }
+
+    /// Every group with more than one post, sorted by its lowest id.
+    pub fn groups(mut self) -> Vec<Vec<u32>> {
+        let posts: Vec<u32> = self.parents.keys().copied().collect();
+        let mut groups: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for post in posts {
+            let root = self.root(post);
+            groups.entry(root).or_default().push(post);
+        }
+
+        groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort();
+                group
+            })
+            .collect()
+    }
+}
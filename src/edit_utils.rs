@@ -0,0 +1,203 @@
+// edit_utils.rs
+
+use crate::error_utils::is_version_conflict;
+use crate::post_utils::merge_source;
+use crate::SettingsConfig;
+use errors::SzurubooruClientError;
+use indicatif::ProgressBar;
+use models::{CreateUpdatePost, PostResource, PostSafety};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use szurubooru_client::*;
+use tokio::time::{sleep, Duration};
+
+/// An update for one post and a readable line per changed field.
+pub struct PostChange {
+    pub update: CreateUpdatePost,
+    pub changes: Vec<String>,
+}
+
+/// An empty update that leaves every field of the post as it is.
+pub fn empty_update(post: &PostResource) -> CreateUpdatePost {
+    CreateUpdatePost {
+        version: post.version,
+        tags: None,
+        safety: None,
+        source: None,
+        relations: None,
+        notes: None,
+        flags: None,
+        content_url: None,
+        content_token: None,
+        anonymous: None,
+    }
+}
+
+pub fn tag_names(post: &PostResource) -> Vec<String> {
+    post.tags
+        .iter()
+        .flatten()
+        .filter_map(|tag| tag.names.first().cloned())
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct PostEdit {
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    pub safety: Option<PostSafety>,
+    pub add_sources: Vec<String>,
+    pub add_relations: Vec<u32>,
+}
+
+impl PostEdit {
+    pub fn is_empty(&self) -> bool {
+        self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
+            && self.safety.is_none()
+            && self.add_sources.is_empty()
+            && self.add_relations.is_empty()
+    }
+
+    /// The update this edit makes to `post`, `None` if the post already matches.
+    pub fn change_for(&self, post: &PostResource) -> Option<PostChange> {
+        let mut update = empty_update(post);
+        let mut changes = Vec::new();
+
+        let tags = tag_names(post);
+        let added: Vec<&String> = self.add_tags.iter().filter(|tag| !tags.contains(tag)).collect();
+        let removed: Vec<&String> = self.remove_tags.iter().filter(|tag| tags.contains(tag)).collect();
+        if !added.is_empty() || !removed.is_empty() {
+            let mut new_tags: Vec<String> = tags.iter().filter(|tag| !removed.contains(tag)).cloned().collect();
+            new_tags.extend(added.iter().map(|tag| tag.to_string()));
+            changes.push(format!(
+                "tags: {}",
+                added.iter().map(|tag| format!("+{}", tag))
+                    .chain(removed.iter().map(|tag| format!("-{}", tag)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+            update.tags = Some(new_tags);
+        }
+
+        if let Some(safety) = self.safety.as_ref().filter(|safety| post.safety.as_ref() != Some(*safety)) {
+            changes.push(format!("safety: {:?} -> {:?}", post.safety, safety));
+            update.safety = Some(safety.clone());
+        }
+
+        let sources: HashSet<&str> = post.source.iter().flat_map(|source| source.lines()).collect();
+        let new_sources: Vec<&String> = self.add_sources.iter().filter(|source| !sources.contains(source.as_str())).collect();
+        if !new_sources.is_empty() {
+            changes.push(format!("sources: +{}", new_sources.iter().map(|source| source.as_str()).collect::<Vec<_>>().join(" +")));
+            update.source = merge_source(post.source.clone(), Some(self.add_sources.join("\n")));
+        }
+
+        let relations: Vec<u32> = post.relations.iter().flatten().map(|relation| relation.id).collect();
+        let new_relations: Vec<u32> = self
+            .add_relations
+            .iter()
+            .copied()
+            .filter(|relation| !relations.contains(relation) && Some(*relation) != post.id)
+            .collect();
+        if !new_relations.is_empty() {
+            changes.push(format!("relations: +{}", new_relations.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" +")));
+            update.relations = Some(relations.into_iter().chain(new_relations).collect());
+        }
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(PostChange { update, changes })
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EditSummary {
+    pub changed: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+}
+
+impl fmt::Display for EditSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} changed, {} unchanged, {} failed", self.changed, self.unchanged, self.failed)
+    }
+}
+
+/// Applies `change_for` to every post in `post_ids`. Posts are fetched right before they are
+/// updated and refetched on a version conflict, so concurrent edits are never overwritten.
+/// On a dry run the changes are only reported. Each change is written to `report` if given.
+pub async fn edit_posts<F>(
+    client: &SzurubooruClient,
+    post_ids: &[u32],
+    change_for: F,
+    dry_run: bool,
+    report: Option<&str>,
+    settings: &SettingsConfig,
+) -> SzurubooruResult<EditSummary>
+where
+    F: Fn(&PostResource) -> Option<PostChange>,
+{
+    let progress_bar = ProgressBar::new(post_ids.len() as u64);
+    let mut summary = EditSummary::default();
+    let mut report_lines = Vec::new();
+
+    for post_id in post_ids {
+        progress_bar.inc(1);
+
+        let mut attempts = 0;
+        let result = loop {
+            let post = match client.request().get_post(*post_id).await {
+                Ok(post) => post,
+                Err(e) => break Err(e),
+            };
+            let Some(change) = change_for(&post) else {
+                break Ok(None);
+            };
+            if dry_run {
+                break Ok(Some(change.changes));
+            }
+
+            match client.request().update_post(*post_id, &change.update).await {
+                Ok(_) => break Ok(Some(change.changes)),
+                Err(e) if is_version_conflict(&e) && attempts < settings.conflict_retries() => attempts += 1,
+                Err(e) => break Err(e),
+            }
+        };
+
+        match result {
+            Ok(None) => summary.unchanged += 1,
+            Ok(Some(changes)) => {
+                summary.changed += 1;
+                let line = format!("post {}: {}", post_id, changes.join("; "));
+                if dry_run || report.is_none() {
+                    progress_bar.println(&line);
+                }
+                report_lines.push(line);
+                if !dry_run {
+                    sleep(Duration::from_millis(settings.timeout)).await;
+                }
+            }
+            Err(e) => {
+                summary.failed += 1;
+                progress_bar.println(format!("Error editing post {}: {}", post_id, e));
+                report_lines.push(format!("post {}: failed: {}", post_id, e));
+                if !settings.skip_on_error {
+                    progress_bar.abandon();
+                    return Err(e);
+                }
+            }
+        }
+    }
+    progress_bar.finish();
+
+    if let Some(report_path) = report {
+        let content: String = report_lines.iter().map(|line| format!("{}\n", line)).collect();
+        fs::write(report_path, content).map_err(|e| SzurubooruClientError::IOError(e))?;
+        println!("Wrote report to {}", report_path);
+    }
+
+    Ok(summary)
+}
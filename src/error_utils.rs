@@ -0,0 +1,32 @@
+// error_utils.rs
+
+use errors::{SzurubooruClientError, SzurubooruServerErrorType};
+use std::io::{Error, ErrorKind};
+use szurubooru_client::*;
+
+/// True when the server reported that the requested resource does not exist.
+pub fn is_not_found(error: &SzurubooruClientError) -> bool {
+    matches!(
+        error,
+        SzurubooruClientError::SzurubooruServerError(e)
+            if matches!(
+                e.name,
+                SzurubooruServerErrorType::TagNotFoundError
+                    | SzurubooruServerErrorType::PostNotFoundError
+                    | SzurubooruServerErrorType::TagCategoryNotFoundError
+            )
+    )
+}
+
+/// True when the server rejected an update because the resource changed since its version was read.
+pub fn is_version_conflict(error: &SzurubooruClientError) -> bool {
+    matches!(
+        error,
+        SzurubooruClientError::SzurubooruServerError(e) if e.name == SzurubooruServerErrorType::IntegrityError
+    )
+}
+
+/// An error for a missing or malformed command line argument.
+pub fn invalid_input(message: impl Into<String>) -> SzurubooruClientError {
+    SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidInput, message.into()))
+}
@@ -2,30 +2,47 @@ use errors::SzurubooruClientError;
 use models::MergePost;
 use post_utils::get_files;
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error as ErrError;
-use std::io::{Error, ErrorKind};
+use std::io::{Error, ErrorKind, Write};
 use std::{env, fs, io};
 use std::path::{Path, PathBuf};
 use szurubooru_client::*;
+use szurubooru_client::tokens::QueryToken;
 use tokio::time::{sleep, Duration};
 use indicatif::{ProgressBar, ProgressStyle};
 
+mod category_utils;
+mod cbz_utils;
+mod cli_utils;
+mod csv_utils;
+mod danbooru_utils;
+mod duplicate_utils;
+mod edit_utils;
+mod error_utils;
+mod merge_utils;
+mod page_utils;
+mod pool_utils;
 mod post_utils;
+mod rule_utils;
+mod snapshot_utils;
+mod source_utils;
+mod tag_utils;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn ErrError>> {
     let config =  load_or_create_config()?;
 
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 4 {
+    let args = cli_utils::Args::parse(env::args().skip(1));
+    if args.positionals.len() < 2 {
         eprintln!("Usage: <operation> <element> <path> [options]");
         return Ok(()); // Return Ok(()) to match the function signature
     }
 
-    let operation = &args[1];
-    let element = &args[2];
-    let path = &args[3];
-    let option = args.get(4);
+    let operation = &args.positionals[0];
+    let element = &args.positionals[1];
+    let path = args.positionals.get(2).map(String::as_str).unwrap_or_default();
+    let option = args.positionals.get(3);
 
     let client = SzurubooruClient::new_with_token(
         config.server.url.as_str(), 
@@ -40,27 +57,191 @@ async fn main() -> Result<(), Box<dyn ErrError>> {
             Ok(())
         }
         "list" if element == "tag_category" => {
-            list_tags_of_category(&client, path, option.unwrap()).await;
+            let Some(category) = option else {
+                eprintln!("Usage: list tag_category <file> <category>");
+                return Ok(());
+            };
+            match list_tags_of_category(&client, path, category).await {
+                Ok(count) => println!("Listed {} tags of category {}.", count, category),
+                Err(e) => eprintln!("Error listing tags: {}", e),
+            }
+            Ok(())
+        }
+        "category" => {
+            if let Err(e) = manage_category(&client, element, &args, &config).await {
+                eprintln!("Error managing tag categories: {}", e);
+            }
+            Ok(())
+        }
+        "export" if element == "tags" => {
+            let query = args.flag_value("--query").map(cli_utils::parse_query).unwrap_or_default();
+            match export_tags(&client, path, &query).await {
+                Ok(count) => println!("Exported {} tags.", count),
+                Err(e) => eprintln!("Error exporting tags: {}", e),
+            }
+            Ok(())
+        }
+        "import" if element == "tags" => {
+            match import_tags(&client, path, args.has_flag("--dry-run"), &config).await {
+                Ok(summary) => println!("Finished importing tags: {}.", summary),
+                Err(e) => eprintln!("Error importing tags: {}", e),
+            }
+            Ok(())
+        }
+        "import" if element == "danbooru" => {
+            let min_usage = args
+                .flag_value("--min-usage")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(config.danbooru.min_usage);
+            match import_danbooru(&client, path, min_usage, args.has_flag("--dry-run"), &config).await {
+                Ok(summary) => println!("Finished importing Danbooru tags: {}.", summary),
+                Err(e) => eprintln!("Error importing Danbooru tags: {}", e),
+            }
+            Ok(())
+        }
+        "merge" if element == "tag" => {
+            match merge_tags(&client, path, args.has_flag("--alias"), &config).await {
+                Ok(affected) => println!("Finished merging tags, {} post tags affected.", affected),
+                Err(e) => eprintln!("Error merging tags: {}", e),
+            }
+            Ok(())
+        }
+        "rename" if element == "tag" => {
+            let keep_alias = !args.has_flag("--no-alias");
+            match rename_tags(&client, path, option, keep_alias, args.has_flag("--dry-run"), &config).await {
+                Ok(renamed) => println!("Finished renaming tags, {} renamed.", renamed),
+                Err(e) => eprintln!("Error renaming tags: {}", e),
+            }
+            Ok(())
+        }
+        "prune" if element == "tags" => {
+            let options = PruneOptions {
+                max_usages: args
+                    .flag_value("--max-usages")
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0),
+                excluded_categories: args
+                    .flag_value("--exclude-category")
+                    .map(|value| value.split(',').map(|category| category.trim().to_string()).collect())
+                    .unwrap_or_default(),
+                keep_implications: args.has_flag("--keep-implications"),
+                assume_yes: args.has_flag("--yes"),
+            };
+            match prune_tags(&client, path, &options, &config).await {
+                Ok(deleted) => println!("Finished pruning tags, {} deleted.", deleted),
+                Err(e) => eprintln!("Error pruning tags: {}", e),
+            }
+            Ok(())
+        }
+        "apply" if element == "implications" => {
+            match apply_implications(&client, path, args.has_flag("--dry-run"), &config).await {
+                Ok(summary) => println!("Finished applying implications: {}.", summary),
+                Err(e) => eprintln!("Error applying implications: {}", e),
+            }
             Ok(())
         }
         "upload" if element == "post" => {
-            match upload_posts(&client, path, config).await {
+            let group_by = args
+                .flag_value("--group-by")
+                .map(String::from)
+                .or_else(|| args.has_flag("--pools").then(|| config.pools.group_key.clone().unwrap_or("id".to_string())));
+            match upload_posts(&client, path, group_by.as_deref(), config).await {
                 Ok(_) => println!("Finished uploading posts."),
                 Err(e) => eprintln!("Error uploading posts: {}", e),
             }
             Ok(())
         }
         "upload" if element == "pool" => {
-            upload_pool(&client, path).await;
+            match upload_pool(&client, path, config).await {
+                Ok(pool_id) => println!("Finished uploading pool {}.", pool_id),
+                Err(e) => eprintln!("Error uploading pool: {}", e),
+            }
+            Ok(())
+        }
+        "create" if element == "pool" => {
+            match create_pool_from_posts(&client, path, &args, &config).await {
+                Ok(pool_id) => println!("Created pool {}.", pool_id),
+                Err(e) => eprintln!("Error creating pool: {}", e),
+            }
+            Ok(())
+        }
+        "update" if element == "pool" => {
+            match update_pool_from_posts(&client, path, &args).await {
+                Ok(count) => println!("Updated pool {}, it now has {} posts.", path, count),
+                Err(e) => eprintln!("Error updating pool: {}", e),
+            }
+            Ok(())
+        }
+        "export" if element == "pool" => {
+            let Some(dest) = option else {
+                eprintln!("Usage: export pool <pool id> <destination folder> [--cbz]");
+                return Ok(());
+            };
+            match export_pool(&client, path, dest, args.has_flag("--cbz"), &config).await {
+                Ok(count) => println!("Exported {} pages.", count),
+                Err(e) => eprintln!("Error exporting pool: {}", e),
+            }
+            Ok(())
+        }
+        "audit" if element == "pools" => {
+            match audit_pools(&client, path).await {
+                Ok(issues) => println!("Finished auditing pools, {} issues found.", issues),
+                Err(e) => eprintln!("Error auditing pools: {}", e),
+            }
+            Ok(())
+        }
+        "find" if element == "duplicates" => {
+            match find_duplicates(&client, path, &args, &config).await {
+                Ok(pairs) => println!("Wrote {} merge pairs to {}.", pairs, path),
+                Err(e) => eprintln!("Error finding duplicates: {}", e),
+            }
             Ok(())
         }
         "merge" if element == "post" => {
-            match merge_posts(&client, path, config).await {
+            let replace_mode = args.flag_value("--replace").unwrap_or("keep");
+            let Some(replace_mode) = post_utils::ReplaceContent::parse(replace_mode) else {
+                eprintln!("--replace must be keep, replace or auto");
+                return Ok(());
+            };
+            let options = MergeOptions {
+                replace_mode,
+                preview: args.has_flag("--preview"),
+                report: args.flag_value("--report").map(String::from),
+            };
+            match merge_posts(&client, path, options, config).await {
                 Ok(_) => println!("Finished merging posts."),
                 Err(e) => eprintln!("Error merging posts: {}", e),
             }
             Ok(())
         }
+        "edit" if element == "post" => {
+            match edit_posts(&client, &args, &config).await {
+                Ok(summary) => println!("Finished editing posts: {}.", summary),
+                Err(e) => eprintln!("Error editing posts: {}", e),
+            }
+            Ok(())
+        }
+        "rewrite" if element == "sources" => {
+            match rewrite_sources(&client, &args, &config).await {
+                Ok(summary) => println!("Finished rewriting sources: {}.", summary),
+                Err(e) => eprintln!("Error rewriting sources: {}", e),
+            }
+            Ok(())
+        }
+        "delete" if element == "post" => {
+            match delete_posts(&client, path, &args, &config).await {
+                Ok(deleted) => println!("Finished deleting posts, {} deleted.", deleted),
+                Err(e) => eprintln!("Error deleting posts: {}", e),
+            }
+            Ok(())
+        }
+        "replace" if element == "post" => {
+            match replace_posts(&client, path, option.map(String::as_str), &args, &config).await {
+                Ok(replaced) => println!("Finished replacing content, {} posts updated.", replaced),
+                Err(e) => eprintln!("Error replacing content: {}", e),
+            }
+            Ok(())
+        }
         _ => {
             eprintln!("Invalid operation or element");
             Ok(())
@@ -76,20 +257,496 @@ async fn set_tags_to_category(client: &SzurubooruClient, path: &str, option: &st
     }
 }
 
-async fn list_tags_of_category(client: &SzurubooruClient, path: &str, option: &str) {
+async fn list_tags_of_category(client: &SzurubooruClient, path: &str, category: &str) -> SzurubooruResult<usize> {
     let path_obj = Path::new(path);
     if path_obj.is_dir() {
-        eprintln!("Error: Expected a file, but a directory was provided for tag operation");
-        return;
+        return Err(SzurubooruClientError::IOError(Error::new(
+            ErrorKind::InvalidInput,
+            "Expected a file, but a directory was provided for tag operation",
+        )));
+    }
+
+    let query = vec![QueryToken::token("category", category)];
+    let tags: Vec<tag_utils::Tag> = tag_utils::fetch_tags(client, &query)
+        .await?
+        .into_iter()
+        .filter_map(tag_utils::Tag::from_resource)
+        .collect();
+
+    tag_utils::write_tags(path, &tags)?;
+    Ok(tags.len())
+}
+
+async fn manage_category(
+    client: &SzurubooruClient,
+    action: &str,
+    args: &cli_utils::Args,
+    config: &Config,
+) -> SzurubooruResult<()> {
+    if action == "list" {
+        for category in category_utils::list_categories(client).await? {
+            println!("{}", category_utils::format_category(&category));
+        }
+        return Ok(());
+    }
+    if action == "apply" {
+        let changed = category_utils::apply_categories(client, &config.tag_categories, args.has_flag("--dry-run")).await?;
+        println!("Applied tag categories from config, {} changed.", changed);
+        return Ok(());
+    }
+
+    let name = args.positionals.get(2).ok_or_else(|| {
+        SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidInput, "A category name is required."))
+    })?;
+    let mut update = models::CreateUpdateTagCategory {
+        version: None,
+        name: None,
+        color: args.flag_value("--color").map(String::from),
+        order: args.flag_value("--order").and_then(|value| value.parse().ok()),
+    };
+
+    match action {
+        "create" => {
+            update.name = Some(name.clone());
+            client.request().create_tag_category(&update).await?;
+            println!("Created category {}.", name);
+        }
+        "update" => {
+            update.version = Some(category_utils::require_category(client, name).await?.version);
+            update.name = args.flag_value("--name").map(String::from);
+            client.request().update_tag_category(name, &update).await?;
+            println!("Updated category {}.", name);
+        }
+        "delete" => {
+            let version = category_utils::require_category(client, name).await?.version;
+            client.request().delete_tag_category(name, version).await?;
+            println!("Deleted category {}.", name);
+        }
+        "set-default" => {
+            client.request().set_default_tag_category(name).await?;
+            println!("Category {} is now the default.", name);
+        }
+        _ => eprintln!("Invalid category action, expected list, create, update, delete, set-default or apply"),
+    }
+
+    Ok(())
+}
+
+async fn export_tags(client: &SzurubooruClient, path: &str, query: &Vec<QueryToken>) -> SzurubooruResult<usize> {
+    let tags: Vec<tag_utils::Tag> = tag_utils::fetch_tags(client, query)
+        .await?
+        .into_iter()
+        .filter_map(tag_utils::Tag::from_resource)
+        .collect();
+
+    tag_utils::write_tags(path, &tags)?;
+    Ok(tags.len())
+}
+
+async fn import_tags(
+    client: &SzurubooruClient,
+    path: &str,
+    dry_run: bool,
+    config: &Config,
+) -> SzurubooruResult<tag_utils::ApplySummary> {
+    let tags = tag_utils::read_tags(path)?;
+    println!("Read {} tag definitions from {}", tags.len(), path);
+
+    tag_utils::apply_tags(client, &tags, dry_run, &config.settings).await
+}
+
+async fn import_danbooru(
+    client: &SzurubooruClient,
+    path: &str,
+    min_usage: u32,
+    dry_run: bool,
+    config: &Config,
+) -> SzurubooruResult<tag_utils::ApplySummary> {
+    let folder = Path::new(path);
+    if !folder.is_dir() {
+        return Err(SzurubooruClientError::IOError(Error::new(
+            ErrorKind::InvalidInput,
+            "Expected a folder containing tags.csv",
+        )));
+    }
+
+    let mut local_names = HashMap::new();
+    for tag in tag_utils::fetch_tags(client, &Vec::new()).await? {
+        let Some(tag) = tag_utils::Tag::from_resource(tag) else {
+            continue;
+        };
+        for name in std::iter::once(&tag.name).chain(tag.aliases.iter()) {
+            local_names.insert(name.to_lowercase(), tag.name.clone());
+        }
+    }
+    println!("Found {} local tag names", local_names.len());
+
+    let dump = danbooru_utils::read_dump(folder, &local_names, &config.danbooru, min_usage)?;
+    for (antecedent, consequent) in &dump.conflicting_aliases {
+        println!("Skipping alias {} -> {}: {} is already a separate tag, merge it instead", antecedent, consequent, antecedent);
+    }
+    println!("Read {} tags from the dump", dump.tags.len());
+
+    tag_utils::apply_tags(client, &dump.tags, dry_run, &config.settings).await
+}
+
+async fn merge_tags(client: &SzurubooruClient, path: &str, add_alias: bool, config: &Config) -> SzurubooruResult<u32> {
+    let tag_pairs = tag_utils::read_name_pairs(path)?;
+    let total_pairs = tag_pairs.len();
+    let mut affected_posts = 0;
+    let mut snapshot = snapshot_utils::Snapshot::create(config.settings.audit_dir(), "merge_tag")?;
+
+    for (count, (remove_tag, merge_to_tag)) in tag_pairs.iter().enumerate() {
+        let result = async {
+            for name in [remove_tag, merge_to_tag] {
+                if let Some((tag, _)) = tag_utils::fetch_tag(client, name).await? {
+                    snapshot.record("tag", &tag.name, &tag)?;
+                }
+            }
+            tag_utils::merge_tag_pair(client, remove_tag, merge_to_tag, add_alias).await
+        };
+
+        match result.await {
+            Ok(usages) => {
+                affected_posts += usages;
+                println!(
+                    "Merged {} into {} ({} posts) | {}/{}",
+                    remove_tag, merge_to_tag, usages, count + 1, total_pairs
+                );
+            }
+            Err(e) => {
+                eprintln!("Error merging {} into {}: {}", remove_tag, merge_to_tag, e);
+                if !config.settings.skip_on_error {
+                    return Err(e);
+                }
+            }
+        }
+
+        sleep(Duration::from_millis(config.settings.timeout)).await;
+    }
+
+    println!("Saved tag definitions to {}", snapshot.path().display());
+    Ok(affected_posts)
+}
+
+/// Renames a single tag, or every `old new` pair in the file at `path` when no new name is given.
+async fn rename_tags(
+    client: &SzurubooruClient,
+    path: &str,
+    new_name: Option<&String>,
+    keep_alias: bool,
+    dry_run: bool,
+    config: &Config,
+) -> SzurubooruResult<usize> {
+    let renames = match new_name {
+        Some(new_name) => vec![(path.to_string(), new_name.clone())],
+        None => tag_utils::read_name_pairs(path)?,
+    };
+    let mut renamed = 0;
+    let mut snapshot = if dry_run {
+        None
+    } else {
+        Some(snapshot_utils::Snapshot::create(config.settings.audit_dir(), "rename_tag")?)
+    };
+
+    for (old_name, new_name) in &renames {
+        match tag_utils::rename_tag(client, old_name, new_name, keep_alias, dry_run, snapshot.as_mut()).await {
+            Ok(_) => renamed += 1,
+            Err(e) => {
+                eprintln!("Error renaming {} to {}: {}", old_name, new_name, e);
+                if !config.settings.skip_on_error {
+                    return Err(e);
+                }
+            }
+        }
+
+        if !dry_run {
+            sleep(Duration::from_millis(config.settings.timeout)).await;
+        }
     }
-    // Your logic to list tags of a category into a file
-    println!("Listing tags of category at path: {}", todo!());
+
+    if let Some(snapshot) = snapshot.filter(|snapshot| snapshot.records() > 0) {
+        println!("Saved merged tag definitions to {}", snapshot.path().display());
+    }
+
+    Ok(renamed)
 }
 
-async fn upload_posts(client: &SzurubooruClient, path: &str, config: Config) -> SzurubooruResult<Vec<u32>> {
-    let files = get_files(path).unwrap();
+/// Applies implication and suggestion rules after checking that every tag exists
+/// and that the rules together with the current implications do not form a cycle.
+async fn apply_implications(
+    client: &SzurubooruClient,
+    path: &str,
+    dry_run: bool,
+    config: &Config,
+) -> SzurubooruResult<tag_utils::ApplySummary> {
+    let rules = rule_utils::read_rules(path)?;
+
+    // Resolve every name (or alias) to the tag on the server
+    let mut tags: HashMap<String, Option<tag_utils::Tag>> = HashMap::new();
+    for rule in &rules {
+        for name in [&rule.antecedent, &rule.consequent] {
+            if !tags.contains_key(name) {
+                let tag = tag_utils::fetch_tag(client, name).await?.map(|(tag, _)| tag);
+                tags.insert(name.clone(), tag);
+            }
+        }
+    }
+
+    let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+    for tag in tags.values().flatten() {
+        graph
+            .entry(tag.name.to_lowercase())
+            .or_default()
+            .extend(tag.implications.iter().map(|name| name.to_lowercase()));
+    }
+
+    // Follow the existing implications all the way down, a loop may close through tags not in the rules
+    let mut pending: Vec<String> = graph.values().flatten().filter(|name| !graph.contains_key(*name)).cloned().collect();
+    while let Some(name) = pending.pop() {
+        if graph.contains_key(&name) {
+            continue;
+        }
+        let implications: Vec<String> = match tag_utils::fetch_tag(client, &name).await? {
+            Some((tag, _)) => tag.implications.iter().map(|implied| implied.to_lowercase()).collect(),
+            None => Vec::new(),
+        };
+        pending.extend(implications.iter().filter(|implied| !graph.contains_key(*implied)).cloned());
+        graph.insert(name, implications);
+        sleep(Duration::from_millis(config.settings.timeout)).await;
+    }
+
+    let mut wanted: Vec<tag_utils::Tag> = Vec::new();
+    for rule in &rules {
+        let (Some(antecedent), Some(consequent)) = (&tags[&rule.antecedent], &tags[&rule.consequent]) else {
+            eprintln!(
+                "Skipping line {}: {}",
+                rule.line,
+                [&rule.antecedent, &rule.consequent]
+                    .iter()
+                    .filter(|name| tags[**name].is_none())
+                    .map(|name| format!("{} does not exist", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            continue;
+        };
+
+        if rule.kind == rule_utils::RuleKind::Implication {
+            graph
+                .entry(antecedent.name.to_lowercase())
+                .or_default()
+                .push(consequent.name.to_lowercase());
+        }
+
+        let index = match wanted.iter().position(|tag| tag.name == antecedent.name) {
+            Some(index) => index,
+            None => {
+                wanted.push(tag_utils::Tag {
+                    name: antecedent.name.clone(),
+                    category: String::new(),
+                    aliases: Vec::new(),
+                    implications: Vec::new(),
+                    suggested: Vec::new(),
+                    usages: 0,
+                });
+                wanted.len() - 1
+            }
+        };
+        match rule.kind {
+            rule_utils::RuleKind::Implication => wanted[index].implications.push(consequent.name.clone()),
+            rule_utils::RuleKind::Suggestion => wanted[index].suggested.push(consequent.name.clone()),
+        }
+    }
+
+    if let Some(cycle) = rule_utils::find_cycle(&graph) {
+        return Err(SzurubooruClientError::IOError(Error::new(
+            ErrorKind::InvalidData,
+            format!("Implications would form a cycle: {}", cycle.join(" -> ")),
+        )));
+    }
+
+    tag_utils::apply_tags(client, &wanted, dry_run, &config.settings).await
+}
+
+struct PruneOptions {
+    max_usages: u32,
+    excluded_categories: Vec<String>,
+    keep_implications: bool,
+    assume_yes: bool,
+}
+
+async fn prune_tags(
+    client: &SzurubooruClient,
+    path: &str,
+    options: &PruneOptions,
+    config: &Config,
+) -> SzurubooruResult<usize> {
+    let query = vec![QueryToken::token("usages", format!("..{}", options.max_usages))];
+    let candidates: Vec<(tag_utils::Tag, u32)> = tag_utils::fetch_tags(client, &query)
+        .await?
+        .into_iter()
+        .filter_map(|resource| {
+            let version = resource.version;
+            tag_utils::Tag::from_resource(resource).map(|tag| (tag, version))
+        })
+        .filter(|(tag, _)| tag.usages <= options.max_usages)
+        .filter(|(tag, _)| !options.excluded_categories.contains(&tag.category))
+        .filter(|(tag, _)| {
+            !options.keep_implications || (tag.implications.is_empty() && tag.suggested.is_empty())
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        println!("No tags with {} or fewer usages found.", options.max_usages);
+        return Ok(0);
+    }
+
+    let tags: Vec<tag_utils::Tag> = candidates.iter().map(|(tag, _)| tag.clone()).collect();
+    tag_utils::write_tags(path, &tags)?;
+    println!("Wrote {} candidate tags to {}", tags.len(), path);
+
+    if !options.assume_yes {
+        let prompt = format!("Delete the tags listed in {}? Remove lines from the file first to keep those tags.", path);
+        if !cli_utils::confirm(&prompt).map_err(|e| SzurubooruClientError::IOError(e))? {
+            println!("No tags deleted.");
+            return Ok(0);
+        }
+    }
+
+    // The list may have been edited while the prompt was open
+    let listed = tag_utils::read_tag_names(path)?;
+    let to_delete: Vec<&(tag_utils::Tag, u32)> = candidates
+        .iter()
+        .filter(|(tag, _)| listed.contains(&tag.name))
+        .collect();
+    let mut snapshot = snapshot_utils::Snapshot::create(config.settings.audit_dir(), "prune_tags")?;
+    for (tag, _) in &to_delete {
+        snapshot.record("tag", &tag.name, tag)?;
+    }
+    println!("Saved {} tag definitions to {}", snapshot.records(), snapshot.path().display());
+
+    let progress_bar = ProgressBar::new(to_delete.len() as u64);
+    let mut deleted = 0;
+
+    for (tag, version) in to_delete {
+        progress_bar.inc(1);
+
+        match client.request().delete_tag(&tag.name, *version).await {
+            Ok(_) => deleted += 1,
+            Err(e) => {
+                progress_bar.println(format!("Error deleting tag {}: {}", tag.name, e));
+                if !config.settings.skip_on_error {
+                    progress_bar.abandon();
+                    return Err(e);
+                }
+            }
+        }
+
+        sleep(Duration::from_millis(config.settings.timeout)).await;
+    }
+
+    progress_bar.finish();
+    Ok(deleted)
+}
+
+async fn upload_posts(
+    client: &SzurubooruClient,
+    path: &str,
+    group_by: Option<&str>,
+    config: Config,
+) -> SzurubooruResult<Vec<u32>> {
+    let files = get_files(path)?;
+
+    // Sidecars are read up front since uploading may delete them
+    let groups: HashMap<PathBuf, pool_utils::SidecarGroup> = match group_by {
+        Some(group_key) => {
+            let page_key = config.pools.page_key.as_deref().unwrap_or("num");
+            files
+                .iter()
+                .filter_map(|file| {
+                    let sidecar = post_utils::read_sidecar(file)?;
+                    let group = pool_utils::sidecar_group(&sidecar, group_key, page_key)?;
+                    Some((file.clone(), group))
+                })
+                .collect()
+        }
+        None => HashMap::new(),
+    };
+
+    let uploaded = upload_files(client, &files, &config).await?;
+    if !groups.is_empty() {
+        create_group_pools(client, &uploaded, &groups, &config).await?;
+    }
+    let post_ids = uploaded.into_iter().map(|(_, post_id)| post_id).collect();
+
+    println!("Finished");
+    if config.settings.delete_folder {
+        match delete_folder(path) {
+            Ok(_) => println!("Folder deleted successfully."),
+            Err(e) => eprintln!("Error deleting folder: {}", e),
+        }
+    }
+
+    Ok(post_ids)
+}
+
+/// Creates or extends one pool per sidecar group, ordering the posts by page.
+/// Groups with a single post only extend a pool that already exists.
+async fn create_group_pools(
+    client: &SzurubooruClient,
+    uploaded: &[(PathBuf, u32)],
+    groups: &HashMap<PathBuf, pool_utils::SidecarGroup>,
+    config: &Config,
+) -> SzurubooruResult<()> {
+    let mut pools: BTreeMap<String, (String, Vec<pool_utils::PoolEntry>)> = BTreeMap::new();
+    for (file, post_id) in uploaded {
+        if let Some(group) = groups.get(file) {
+            pools
+                .entry(group.pool_name.clone())
+                .or_insert_with(|| (group.description.clone(), Vec::new()))
+                .1
+                .push((group.page, *post_id));
+        }
+    }
+
+    for (pool_name, (description, entries)) in pools {
+        let result = async {
+            if entries.len() < 2 && pool_utils::find_pool_by_name(client, &pool_name).await?.is_none() {
+                return Ok(false);
+            }
+            let category = config.pools.category.clone();
+            pool_utils::create_or_extend_pool(client, &pool_name, category, &description, entries).await?;
+            Ok::<_, SzurubooruClientError>(true)
+        };
+
+        match result.await {
+            Ok(true) => println!("Updated pool {}", pool_name),
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Error creating pool {}: {}", pool_name, e);
+                if !config.settings.skip_on_error {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Uploads `files` in the given order and returns each uploaded file with the id of its created or updated post.
+async fn upload_files(
+    client: &SzurubooruClient,
+    files: &[PathBuf],
+    config: &Config,
+) -> SzurubooruResult<Vec<(PathBuf, u32)>> {
     let mut post_ids = Vec::new();
     let mut artists = Vec::new();
+    let mut snapshot = match config.settings.delete_files_in_progress {
+        true => Some(snapshot_utils::Snapshot::create(config.settings.audit_dir(), "upload_post")?),
+        false => None,
+    };
     let total_files_num = files.len();
 
     for (count, file) in files.iter().enumerate() {
@@ -98,13 +755,14 @@ async fn upload_posts(client: &SzurubooruClient, path: &str, config: Config) ->
         println!("Uploading {} | {}/{}", file.to_string_lossy(), count + 1, total_files_num);
 
         loop {
-            match post_utils::create_post(client, &file).await {
+            match post_utils::create_post(client, &file, config.settings.conflict_retries()).await {
                 Ok((post_id, artist)) => {
-                    post_ids.push(post_id);
+                    post_ids.push((file.clone(), post_id));
                     artists.push(artist);
                     println!("Finished {}", file.to_string_lossy());
 
-                    if config.settings.delete_files_in_progress {
+                    if let Some(snapshot) = snapshot.as_mut() {
+                        snapshot.record("local_file", post_id, &local_file_metadata(file, post_id))?;
                         match delete_file(file) {
                             Ok(_) => println!("File deleted successfully."),
                             Err(e) => eprintln!("Error deleting file: {}", e),
@@ -147,20 +805,272 @@ async fn upload_posts(client: &SzurubooruClient, path: &str, config: Config) ->
         sleep(Duration::from_millis(config.settings.timeout)).await;
     }
 
-    println!("Finished");
-    if config.settings.delete_folder {
-        match delete_folder(path) {
-            Ok(_) => println!("Folder deleted successfully."),
-            Err(e) => eprintln!("Error deleting folder: {}", e),
+    Ok(post_ids)
+}
+
+/// Post ids from `--ids <file>` or `--query "<search>"`, in file or search order.
+async fn select_post_ids(client: &SzurubooruClient, args: &cli_utils::Args) -> SzurubooruResult<Vec<u32>> {
+    if let Some(ids_path) = args.flag_value("--ids") {
+        return post_utils::read_ids(ids_path);
+    }
+    if let Some(query) = args.flag_value("--query") {
+        let posts = post_utils::fetch_posts(client, &cli_utils::parse_query(query)).await?;
+        return Ok(posts.into_iter().filter_map(|post| post.id).collect());
+    }
+
+    Err(SzurubooruClientError::IOError(Error::new(
+        ErrorKind::InvalidInput,
+        "Either --ids <file> or --query \"<search>\" is required.",
+    )))
+}
+
+async fn create_pool_from_posts(
+    client: &SzurubooruClient,
+    name: &str,
+    args: &cli_utils::Args,
+    config: &Config,
+) -> SzurubooruResult<u32> {
+    if name.is_empty() {
+        return Err(SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidInput, "A pool name is required.")));
+    }
+
+    let post_ids = select_post_ids(client, args).await?;
+    let category = args.flag_value("--category").map(String::from).or_else(|| config.pools.category.clone());
+    let pool = pool_utils::create_pool(client, name, category, pool_utils::unique_posts(post_ids)).await?;
+
+    Ok(pool.id.unwrap_or_default())
+}
+
+async fn update_pool_from_posts(client: &SzurubooruClient, pool_id: &str, args: &cli_utils::Args) -> SzurubooruResult<usize> {
+    let pool_id: u32 = pool_id.parse().map_err(|_| {
+        SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidInput, "Expected a pool id."))
+    })?;
+    let mode = args.flag_value("--mode").unwrap_or("append");
+    let mode = pool_utils::PoolUpdateMode::parse(mode).ok_or_else(|| {
+        SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidInput, "--mode must be append, prepend or reorder."))
+    })?;
+
+    let post_ids = select_post_ids(client, args).await?;
+    let pool = client.request().get_pool(pool_id).await?;
+    let (posts, ignored) = pool_utils::arrange_posts(&pool_utils::pool_post_ids(&pool), &post_ids, mode);
+    if !ignored.is_empty() {
+        eprintln!(
+            "Ignoring posts that are not in the pool: {}",
+            ignored.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let count = posts.len();
+    pool_utils::update_pool_posts(client, &pool, posts).await?;
+    Ok(count)
+}
+
+/// Downloads the posts of a pool in order as `001.jpg`, `002.png`, ... with a sidecar per page,
+/// and optionally packs them into `<destination>.cbz`.
+async fn export_pool(
+    client: &SzurubooruClient,
+    pool_id: &str,
+    dest: &str,
+    cbz: bool,
+    config: &Config,
+) -> SzurubooruResult<usize> {
+    let pool_id: u32 = pool_id.parse().map_err(|_| {
+        SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidInput, "Expected a pool id."))
+    })?;
+    let pool = client.request().get_pool(pool_id).await?;
+    let post_ids = pool_utils::pool_post_ids(&pool);
+
+    let dest_dir = Path::new(dest);
+    fs::create_dir_all(dest_dir).map_err(|e| SzurubooruClientError::IOError(e))?;
+
+    let width = post_ids.len().to_string().len().max(3);
+    let progress_bar = ProgressBar::new(post_ids.len() as u64);
+    let mut pages = Vec::new();
+
+    for (index, post_id) in post_ids.iter().enumerate() {
+        progress_bar.inc(1);
+
+        let result = async {
+            let post = client.request().get_post(*post_id).await?;
+            let file_name = format!("{:0width$}.{}", index + 1, post_utils::content_extension(&post), width = width);
+            post_utils::download_with_sidecar(client, &post, dest_dir, &file_name).await
+        };
+
+        match result.await {
+            Ok(file_path) => pages.push(file_path),
+            Err(e) => {
+                progress_bar.println(format!("Error exporting post {}: {}", post_id, e));
+                if !config.settings.skip_on_error {
+                    progress_bar.abandon();
+                    return Err(e);
+                }
+            }
         }
+
+        sleep(Duration::from_millis(config.settings.timeout)).await;
     }
+    progress_bar.finish();
 
-    Ok(post_ids)
+    if cbz {
+        let info = cbz_utils::ComicInfo {
+            title: pool.names.as_ref().and_then(|names| names.first().cloned()).unwrap_or_default(),
+            summary: pool.description.clone().unwrap_or_default(),
+            web: format!("{}/pool/{}", config.server.url.trim_end_matches('/'), pool_id),
+            tags: pool.category.iter().cloned().collect(),
+        };
+        // Appended rather than replaced, so `vol.1` becomes `vol.1.cbz`
+        let file_name = dest_dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| "pool".to_string());
+        let cbz_path = dest_dir.with_file_name(format!("{}.cbz", file_name));
+        cbz_utils::write_cbz(&cbz_path, &pages, &info)?;
+        println!("Wrote {}", cbz_path.display());
+    }
+
+    Ok(pages.len())
 }
 
-async fn merge_posts(client: &SzurubooruClient, path: &str, config: Config) -> SzurubooruResult<Vec<u32>> {
-    let posts_ids: Vec<(u32, u32)> = post_utils::read_number_pairs(path)?;
-    let merged_ids: Vec<u32> = posts_ids.iter().map(|(_, b)| *b).collect();    
+/// Reports empty pools, duplicate or deleted entries, posts shared by pools of the same category
+/// and gaps in the page numbers recorded by `upload post --pools`. Writes the report to `path` if given.
+async fn audit_pools(client: &SzurubooruClient, path: &str) -> SzurubooruResult<usize> {
+    let pools = pool_utils::fetch_pools(client).await?;
+    let progress_bar = ProgressBar::new(pools.len() as u64);
+    let mut issues = Vec::new();
+    let mut memberships: BTreeMap<(String, u32), Vec<u32>> = BTreeMap::new();
+
+    for pool in &pools {
+        progress_bar.inc(1);
+        let pool_id = pool.id.unwrap_or_default();
+        let posts = pool_utils::pool_post_ids(pool);
+
+        if posts.is_empty() {
+            issues.push(format!("Pool {} is empty", pool_id));
+            continue;
+        }
+
+        let mut seen = Vec::new();
+        for post in &posts {
+            if seen.contains(post) {
+                issues.push(format!("Pool {} lists post {} more than once", pool_id, post));
+            } else {
+                seen.push(*post);
+            }
+        }
+
+        let query = vec![QueryToken::token("pool", pool_id.to_string())];
+        let existing: Vec<u32> = post_utils::fetch_posts(client, &query)
+            .await?
+            .into_iter()
+            .filter_map(|post| post.id)
+            .collect();
+        for post in seen.iter().filter(|post| !existing.contains(post)) {
+            issues.push(format!("Pool {} lists deleted post {}", pool_id, post));
+        }
+
+        let category = pool.category.clone().unwrap_or_default();
+        for post in &seen {
+            memberships.entry((category.clone(), *post)).or_default().push(pool_id);
+        }
+
+        if let Some(pages) = pool.description.as_deref().and_then(pool_utils::parse_pages) {
+            if pages.len() != posts.len() {
+                issues.push(format!("Pool {} records {} pages but has {} posts", pool_id, pages.len(), posts.len()));
+            }
+            let missing = pool_utils::missing_pages(&pages);
+            if !missing.is_empty() {
+                issues.push(format!(
+                    "Pool {} is missing pages {}",
+                    pool_id,
+                    missing.iter().map(|page| page.to_string()).collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+    }
+    progress_bar.finish();
+
+    for ((category, post), pool_ids) in memberships.iter().filter(|(_, pool_ids)| pool_ids.len() > 1) {
+        issues.push(format!(
+            "Post {} is in several {} pools: {}",
+            post,
+            category,
+            pool_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    let report: String = issues.iter().map(|issue| format!("{}\n", issue)).collect();
+    if path.is_empty() {
+        print!("{}", report);
+    } else {
+        fs::write(path, report).map_err(|e| SzurubooruClientError::IOError(e))?;
+        println!("Wrote report to {}", path);
+    }
+
+    Ok(issues.len())
+}
+
+struct MergeOptions {
+    replace_mode: post_utils::ReplaceContent,
+    preview: bool,
+    report: Option<String>,
+}
+
+async fn merge_posts(
+    client: &SzurubooruClient,
+    path: &str,
+    options: MergeOptions,
+    config: Config,
+) -> SzurubooruResult<Vec<u32>> {
+    let resolved = merge_utils::resolve_merge_pairs(post_utils::read_merge_pairs(path)?);
+    for (remove_post, chain) in &resolved.collapsed {
+        println!(
+            "Collapsed chain {} to merge {} into {}",
+            chain.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" -> "),
+            remove_post,
+            chain.last().unwrap_or(remove_post)
+        );
+    }
+    for problem in &resolved.problems {
+        eprintln!("{}", problem);
+    }
+    if !resolved.problems.is_empty() && !config.settings.skip_on_error {
+        return Err(SzurubooruClientError::IOError(Error::new(
+            ErrorKind::InvalidData,
+            "The pairs file contains merge cycles or contradictions.",
+        )));
+    }
+    let mut posts_ids: Vec<post_utils::MergePair> = resolved.pairs;
+
+    // Check every post exists before the first merge runs
+    let all_ids: Vec<u32> = posts_ids.iter().flat_map(|pair| [pair.remove_post, pair.merge_to_post]).collect();
+    let (posts, missing) = post_utils::fetch_posts_by_id(client, &all_ids).await?;
+    if !missing.is_empty() {
+        let missing_list = missing.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+        if !config.settings.skip_on_error {
+            return Err(SzurubooruClientError::IOError(Error::new(
+                ErrorKind::NotFound,
+                format!("Posts do not exist: {}", missing_list),
+            )));
+        }
+        eprintln!("Skipping pairs with posts that do not exist: {}", missing_list);
+        posts_ids.retain(|pair| !missing.contains(&pair.remove_post) && !missing.contains(&pair.merge_to_post));
+    }
+
+    if options.preview {
+        preview_merges(&posts_ids, &posts, &options)?;
+        return Ok(Vec::new());
+    }
+
+    let mut snapshot = snapshot_utils::Snapshot::create(config.settings.audit_dir(), "merge_post")?;
+    let mut snapshotted = HashSet::new();
+    for pair in &posts_ids {
+        for post_id in [pair.remove_post, pair.merge_to_post] {
+            if let Some(post) = posts.get(&post_id).filter(|_| snapshotted.insert(post_id)) {
+                snapshot.record("post", post_id, post)?;
+            }
+        }
+    }
+    println!("Saved metadata of {} posts to {}", snapshot.records(), snapshot.path().display());
+
+    let merged_ids: Vec<u32> = posts_ids.iter().map(|pair| pair.merge_to_post).collect();
+    let mut replaced = Vec::new();
 
     let progress_bar = ProgressBar::new(posts_ids.len() as u64);
 
@@ -182,45 +1092,56 @@ async fn merge_posts(client: &SzurubooruClient, path: &str, config: Config) -> S
 
     progress_bar.set_style(default_style.clone());
 
-    for (remove_post, merge_to_post) in posts_ids {
+    // Outcome of every pair, next to the snapshot of the run
+    let outcomes_path = snapshot.path().with_extension("results.tsv");
+    let mut outcomes = fs::File::create(&outcomes_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    writeln!(outcomes, "remove_post\tmerge_to_post\toutcome\tattempts\tdetail").map_err(|e| SzurubooruClientError::IOError(e))?;
+    let conflict_retries = config.settings.conflict_retries();
+
+    for pair in posts_ids {
         progress_bar.inc(1);
+        let (remove_post, merge_to_post) = (pair.remove_post, pair.merge_to_post);
 
-        let result = async {
-            let remove_post_version = client
-                .request()
-                .get_post(remove_post)
-                .await?
-                .version
-                .ok_or_else(|| SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, "Missing remove_post version.")))?;
-
-            let merge_to_version = client
-                .request()
-                .get_post(merge_to_post)
-                .await?
-                .version
-                .ok_or_else(|| SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, "Missing merge_to_post version.")))?;
-
-            let merge = MergePost {
-                remove_post_version,
-                remove_post,
-                merge_to_version,
-                merge_to_post,
-                replace_post_content: false,
-            };
+        // Versions are refetched on every attempt, so a conflict means someone edited a post in between
+        let mut attempts = 0;
+        let result = loop {
+            attempts += 1;
+            match merge_pair(client, &pair, options.replace_mode).await {
+                Err(e) if error_utils::is_version_conflict(&e) && attempts <= conflict_retries => {
+                    progress_bar.println(format!(
+                        "Post {} or {} was edited during the merge, retrying ({}/{})",
+                        remove_post, merge_to_post, attempts, conflict_retries
+                    ));
+                    sleep(Duration::from_millis(config.settings.timeout)).await;
+                }
+                result => break result,
+            }
+        };
 
-            client.request().merge_post(&merge).await
+        let (status, detail) = match &result {
+            Ok(true) => ("merged", "content replaced".to_string()),
+            Ok(false) => ("merged", String::new()),
+            Err(e) => ("failed", e.to_string().replace(['\t', '\n'], " ")),
         };
+        writeln!(outcomes, "{}\t{}\t{}\t{}\t{}", remove_post, merge_to_post, status, attempts, detail)
+            .map_err(|e| SzurubooruClientError::IOError(e))?;
 
-        if let Err(e) = result.await {
-            progress_bar.set_style(error_style.clone()); // Switch to red style on error
-            progress_bar.set_message("Error encountered.");
-            if !config.settings.skip_on_error {
-                progress_bar.finish_with_message("Error encountered.");
-                return Err(e);
+        match result {
+            Err(e) => {
+                progress_bar.set_style(error_style.clone()); // Switch to red style on error
+                progress_bar.set_message("Error encountered.");
+                if !config.settings.skip_on_error {
+                    progress_bar.finish_with_message("Error encountered.");
+                    return Err(e);
+                }
+            }
+            Ok(replaced_content) => {
+                if replaced_content {
+                    replaced.push(merge_to_post);
+                }
+                progress_bar.set_style(success_style.clone()); // Switch to green style on success
+                progress_bar.set_message("Success");
             }
-        } else {
-            progress_bar.set_style(success_style.clone()); // Switch to green style on success
-            progress_bar.set_message("Success");
         }
 
         sleep(Duration::from_millis(config.settings.timeout)).await;
@@ -230,11 +1151,465 @@ async fn merge_posts(client: &SzurubooruClient, path: &str, config: Config) -> S
     }
 
     progress_bar.finish_with_message("Merge complete.");
+    println!("Wrote the outcome of every pair to {}", outcomes_path.display());
+    if !replaced.is_empty() {
+        println!(
+            "Replaced content of {} posts: {}",
+            replaced.len(),
+            replaced.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+        );
+    }
     Ok(merged_ids)
 }
 
 
 
+/// Applies the `--add-tags`, `--remove-tags`, `--set-safety`, `--add-source` and `--add-relations`
+/// flags to every post picked by `--query` or `--ids`.
+async fn edit_posts(
+    client: &SzurubooruClient,
+    args: &cli_utils::Args,
+    config: &Config,
+) -> SzurubooruResult<edit_utils::EditSummary> {
+    let list = |flag: &str| -> Vec<String> {
+        args.flag_value(flag)
+            .map(|value| value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect())
+            .unwrap_or_default()
+    };
+
+    let safety = match args.flag_value("--set-safety") {
+        Some(value) => Some(
+            post_utils::parse_safety(value).ok_or_else(|| error_utils::invalid_input(format!("Unknown safety: {}", value)))?,
+        ),
+        None => None,
+    };
+    let add_relations = list("--add-relations")
+        .iter()
+        .map(|id| id.parse::<u32>().map_err(|_| error_utils::invalid_input(format!("Invalid post id: {}", id))))
+        .collect::<Result<Vec<u32>, _>>()?;
+    let edit = edit_utils::PostEdit {
+        add_tags: list("--add-tags"),
+        remove_tags: list("--remove-tags"),
+        safety,
+        add_sources: args.flag_value("--add-source").map(String::from).into_iter().collect(),
+        add_relations,
+    };
+    if edit.is_empty() {
+        return Err(error_utils::invalid_input(
+            "Nothing to edit, use --add-tags, --remove-tags, --set-safety, --add-source or --add-relations.".to_string(),
+        ));
+    }
+
+    let post_ids = select_post_ids(client, args).await?;
+    println!("Editing {} posts.", post_ids.len());
+
+    edit_utils::edit_posts(
+        client,
+        &post_ids,
+        |post| edit.change_for(post),
+        args.has_flag("--dry-run"),
+        args.flag_value("--report"),
+        &config.settings,
+    )
+    .await
+}
+
+/// Rewrites the source lines of every post picked by `--query` or `--ids` with the regex
+/// rules from every `--rule` and the rules file from `--rules`, in that order.
+async fn rewrite_sources(
+    client: &SzurubooruClient,
+    args: &cli_utils::Args,
+    config: &Config,
+) -> SzurubooruResult<edit_utils::EditSummary> {
+
+    let mut rules = Vec::new();
+    for rule in args.flag_values("--rule") {
+        rules.push(source_utils::parse_rule(rule).map_err(|e| error_utils::invalid_input(format!("Invalid rule {}: {}", rule, e)))?);
+    }
+    if let Some(rules_path) = args.flag_value("--rules") {
+        rules.extend(source_utils::read_rules(rules_path)?);
+    }
+    if rules.is_empty() {
+        return Err(error_utils::invalid_input("Either --rule 'regex => replacement' or --rules <file> is required."));
+    }
+
+    let post_ids = select_post_ids(client, args).await?;
+    println!("Rewriting the sources of {} posts with {} rules.", post_ids.len(), rules.len());
+
+    let change_for = |post: &models::PostResource| {
+        let before = post.source.as_deref().unwrap_or_default();
+        let after = source_utils::rewrite_source(before, &rules);
+        if before == after.as_deref().unwrap_or_default() {
+            return None;
+        }
+
+        let mut diff = vec!["sources:".to_string()];
+        diff.extend(before.lines().map(|line| format!("  - {}", line)));
+        diff.extend(after.iter().flat_map(|source| source.lines()).map(|line| format!("  + {}", line)));
+
+        let mut update = edit_utils::empty_update(post);
+        update.source = Some(after.unwrap_or_default());
+        Some(edit_utils::PostChange { update, changes: vec![diff.join("\n")] })
+    };
+
+    edit_utils::edit_posts(
+        client,
+        &post_ids,
+        change_for,
+        args.has_flag("--dry-run"),
+        args.flag_value("--report"),
+        &config.settings,
+    )
+    .await
+}
+
+/// Deletes every post picked by `--query` or `--ids` after saving its metadata, and with
+/// `--content` its file and sidecar, to the backup folder (`audit_dir` if none is given).
+/// Posts whose backup failed are kept.
+async fn delete_posts(
+    client: &SzurubooruClient,
+    backup_dir: &str,
+    args: &cli_utils::Args,
+    config: &Config,
+) -> SzurubooruResult<usize> {
+    let backup_dir = if backup_dir.is_empty() { config.settings.audit_dir() } else { backup_dir };
+    let post_ids = select_post_ids(client, args).await?;
+    if post_ids.is_empty() {
+        println!("No posts matched.");
+        return Ok(0);
+    }
+
+    let mut snapshot = snapshot_utils::Snapshot::create(backup_dir, "delete_post")?;
+    let with_content = args.has_flag("--content");
+    let progress_bar = ProgressBar::new(post_ids.len() as u64);
+    let mut backed_up: Vec<(models::PostResource, u32)> = Vec::new();
+    let mut failed: Vec<(u32, String)> = Vec::new();
+
+    for post_id in &post_ids {
+        progress_bar.inc(1);
+
+        let result = async {
+            let post = client.request().get_post(*post_id).await?;
+            let version = post.version.ok_or_else(|| {
+                SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, "Post has no version."))
+            })?;
+            snapshot.record("post", post_id, &post)?;
+
+            if with_content {
+                let file_name = format!("{}.{}", post_id, post_utils::content_extension(&post));
+                post_utils::download_with_sidecar(client, &post, Path::new(backup_dir), &file_name).await?;
+            }
+
+            Ok::<_, SzurubooruClientError>((post, version))
+        };
+
+        match result.await {
+            Ok(backup) => backed_up.push(backup),
+            Err(e) => {
+                progress_bar.println(format!("Error backing up post {}, it will not be deleted: {}", post_id, e));
+                failed.push((*post_id, e.to_string()));
+                if !config.settings.skip_on_error {
+                    progress_bar.abandon();
+                    return Err(e);
+                }
+            }
+        }
+
+        sleep(Duration::from_millis(config.settings.timeout)).await;
+    }
+    progress_bar.finish();
+    println!("Saved {} posts to {}", snapshot.records(), snapshot.path().display());
+
+    println!("{} posts will be deleted, for example:", backed_up.len());
+    for (post, _) in backed_up.iter().take(10) {
+        let tags = edit_utils::tag_names(post);
+        let source = post.source.as_deref().and_then(|source| source.lines().next()).unwrap_or("no source");
+        println!("  {} [{}] {}", post.id.unwrap_or_default(), tags.iter().take(5).cloned().collect::<Vec<_>>().join(" "), source);
+    }
+
+    if !args.has_flag("--yes") {
+        let prompt = format!("Delete these {} posts?", backed_up.len());
+        if !cli_utils::confirm(&prompt).map_err(|e| SzurubooruClientError::IOError(e))? {
+            println!("No posts deleted.");
+            return Ok(0);
+        }
+    }
+
+    let progress_bar = ProgressBar::new(backed_up.len() as u64);
+    let mut deleted = 0;
+
+    for (post, version) in &backed_up {
+        progress_bar.inc(1);
+        let post_id = post.id.unwrap_or_default();
+
+        match client.request().delete_post(post_id, *version).await {
+            Ok(_) => deleted += 1,
+            Err(e) => {
+                progress_bar.println(format!("Error deleting post {}: {}", post_id, e));
+                failed.push((post_id, e.to_string()));
+                if !config.settings.skip_on_error {
+                    progress_bar.abandon();
+                    return Err(e);
+                }
+            }
+        }
+
+        sleep(Duration::from_millis(config.settings.timeout)).await;
+    }
+    progress_bar.finish();
+
+    if !failed.is_empty() {
+        println!("{} posts were not deleted:", failed.len());
+        for (post_id, error) in &failed {
+            println!("  {}: {}", post_id, error);
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Replaces the content of existing posts. `target` is a post id followed by the new file,
+/// a file of `post_id file` lines, or a folder whose file names hold the post id, picked by the
+/// first capture group of `--pattern` (`^(\d+)` by default).
+async fn replace_posts(
+    client: &SzurubooruClient,
+    target: &str,
+    file: Option<&str>,
+    args: &cli_utils::Args,
+    config: &Config,
+) -> SzurubooruResult<usize> {
+
+    let replacements: Vec<(u32, PathBuf)> = if let Ok(post_id) = target.parse::<u32>() {
+        let file = file.ok_or_else(|| error_utils::invalid_input("A file to replace the content with is required."))?;
+        vec![(post_id, PathBuf::from(file))]
+    } else if Path::new(target).is_dir() {
+        let pattern = args.flag_value("--pattern").unwrap_or(r"^(\d+)");
+        let pattern = regex::Regex::new(pattern).map_err(|e| error_utils::invalid_input(format!("Invalid pattern: {}", e)))?;
+        let mut replacements = Vec::new();
+        for file in get_files(target)? {
+            let stem = file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+            let id = pattern
+                .captures(&stem)
+                .and_then(|captures| captures.get(1).or_else(|| captures.get(0)))
+                .and_then(|id| id.as_str().parse::<u32>().ok());
+            match id {
+                Some(post_id) => replacements.push((post_id, file)),
+                None => println!("Skipping {}, no post id found in the file name", file.display()),
+            }
+        }
+        replacements
+    } else {
+        post_utils::read_post_file_pairs(target)?
+    };
+
+    let thumbnail = args.flag_value("--thumbnail").map(PathBuf::from);
+    if thumbnail.is_some() && replacements.len() > 1 {
+        return Err(error_utils::invalid_input("--thumbnail can only be used when replacing a single post."));
+    }
+    if let Some((_, missing)) = replacements.iter().find(|(_, file)| !file.is_file()) {
+        return Err(error_utils::invalid_input(format!("{} is not a file.", missing.display())));
+    }
+
+    let mut snapshot = snapshot_utils::Snapshot::create(config.settings.audit_dir(), "replace_post")?;
+    let progress_bar = ProgressBar::new(replacements.len() as u64);
+    let mut replaced = 0;
+
+    for (post_id, file) in &replacements {
+        progress_bar.inc(1);
+
+        let result = async {
+            let post = client.request().get_post(*post_id).await?;
+            snapshot.record("post", post_id, &post)?;
+            post_utils::replace_content(client, *post_id, file, thumbnail.as_deref(), config.settings.conflict_retries())
+                .await
+        };
+
+        match result.await {
+            Ok(_) => replaced += 1,
+            Err(e) => {
+                progress_bar.println(format!("Error replacing post {} with {}: {}", post_id, file.display(), e));
+                if !config.settings.skip_on_error {
+                    progress_bar.abandon();
+                    return Err(e);
+                }
+            }
+        }
+
+        sleep(Duration::from_millis(config.settings.timeout)).await;
+    }
+    progress_bar.finish();
+
+    Ok(replaced)
+}
+
+/// Reverse searches the content of every post matching `--query`, groups posts that are at least
+/// `--threshold` similar and writes a pairs file merging each group into the post picked by `--keep`.
+async fn find_duplicates(
+    client: &SzurubooruClient,
+    path: &str,
+    args: &cli_utils::Args,
+    config: &Config,
+) -> SzurubooruResult<usize> {
+    if path.is_empty() {
+        return Err(error_utils::invalid_input("A pairs file to write is required."));
+    }
+
+    let query = args.flag_value("--query").map(cli_utils::parse_query).unwrap_or_default();
+    let threshold = args
+        .flag_value("--threshold")
+        .and_then(|value| value.parse().ok())
+        .or(config.duplicates.threshold)
+        .unwrap_or(0.85);
+    let keep = args
+        .flag_value("--keep")
+        .map(String::from)
+        .or_else(|| config.duplicates.keep.clone())
+        .unwrap_or("lowest-id".to_string());
+    let rules = duplicate_utils::parse_keep_rules(&keep)
+        .ok_or_else(|| error_utils::invalid_input("--keep accepts lowest-id, highest-resolution, most-tags and largest-file."))?;
+
+    let posts = post_utils::fetch_posts(client, &query).await?;
+    let progress_bar = ProgressBar::new(posts.len() as u64);
+    let mut known: HashMap<u32, models::PostResource> = HashMap::new();
+    let mut groups = duplicate_utils::DuplicateGroups::default();
+
+    for post in posts {
+        progress_bar.inc(1);
+        let Some(post_id) = post.id else {
+            continue;
+        };
+
+        let temp_path = env::temp_dir().join(format!("oxibooru_toolkit_{}.{}", post_id, post_utils::content_extension(&post)));
+        let result = async {
+            client.request().download_image_to_path(post_id, &temp_path).await?;
+            client.request().reverse_search_file_path(temp_path.clone()).await
+        };
+        let search_result = result.await;
+        let _ = fs::remove_file(&temp_path);
+
+        match search_result {
+            Ok(search_result) => {
+                let similar = search_result
+                    .similar_posts
+                    .into_iter()
+                    // The server reports a distance that shrinks as images get more alike
+                    .filter(|similar_post| 1.0 - similar_post.distance as f64 >= threshold)
+                    .map(|similar_post| similar_post.post);
+                for other in search_result.exact_post.into_iter().chain(similar) {
+                    if let Some(other_id) = other.id.filter(|other_id| *other_id != post_id) {
+                        groups.connect(post_id, other_id);
+                        known.entry(other_id).or_insert(other);
+                    }
+                }
+            }
+            Err(e) => {
+                progress_bar.println(format!("Error searching post {}: {}", post_id, e));
+                if !config.settings.skip_on_error {
+                    progress_bar.abandon();
+                    return Err(e);
+                }
+            }
+        }
+        known.insert(post_id, post);
+
+        sleep(Duration::from_millis(config.settings.timeout)).await;
+    }
+    progress_bar.finish();
+
+    let mut content = format!("# find duplicates, threshold {}, keep {}\n", threshold, keep);
+    let mut pairs = 0;
+    for group in groups.groups() {
+        let members: Vec<&models::PostResource> = group.iter().filter_map(|id| known.get(id)).collect();
+        let keeper = duplicate_utils::choose_keeper(&members, &rules).id.unwrap_or_default();
+
+        content.push_str(&format!("# group {}\n", group.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" ")));
+        for post_id in group.iter().filter(|post_id| **post_id != keeper) {
+            content.push_str(&format!("{} {}\n", post_id, keeper));
+            pairs += 1;
+        }
+    }
+
+    fs::write(path, content).map_err(|e| SzurubooruClientError::IOError(e))?;
+    Ok(pairs)
+}
+
+/// Merges one pair with freshly fetched versions, returning whether the content was replaced.
+async fn merge_pair(
+    client: &SzurubooruClient,
+    pair: &post_utils::MergePair,
+    replace_mode: post_utils::ReplaceContent,
+) -> SzurubooruResult<bool> {
+    let (remove_post, merge_to_post) = (pair.remove_post, pair.merge_to_post);
+
+    let removed = client.request().get_post(remove_post).await?;
+    let remove_post_version = removed
+        .version
+        .ok_or_else(|| SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, "Missing remove_post version.")))?;
+
+    let target = client.request().get_post(merge_to_post).await?;
+    let merge_to_version = target
+        .version
+        .ok_or_else(|| SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, "Missing merge_to_post version.")))?;
+
+    let replace_post_content = pair.replace.unwrap_or(replace_mode).decide(&removed, &target);
+
+    let merge = MergePost {
+        remove_post_version,
+        remove_post,
+        merge_to_version,
+        merge_to_post,
+        replace_post_content,
+    };
+
+    client.request().merge_post(&merge).await?;
+    Ok(replace_post_content)
+}
+
+/// Prints, or writes to `--report`, what every merge would change without merging anything.
+fn preview_merges(
+    pairs: &[post_utils::MergePair],
+    posts: &HashMap<u32, models::PostResource>,
+    options: &MergeOptions,
+) -> SzurubooruResult<()> {
+    let mut report = String::new();
+    let mut flagged = 0;
+
+    for pair in pairs {
+        let (Some(removed), Some(target)) = (posts.get(&pair.remove_post), posts.get(&pair.merge_to_post)) else {
+            continue;
+        };
+        let replace_content = pair.replace.unwrap_or(options.replace_mode).decide(removed, target);
+        let preview = merge_utils::preview_merge(removed, target, replace_content);
+        if !preview.warnings.is_empty() {
+            flagged += 1;
+        }
+        report.push_str(&format!("{}\n", preview));
+    }
+    report.push_str(&format!("{} merges, {} flagged\n", pairs.len(), flagged));
+
+    match &options.report {
+        Some(report_path) => {
+            fs::write(report_path, report).map_err(|e| SzurubooruClientError::IOError(e))?;
+            println!("Wrote merge preview to {}", report_path);
+        }
+        None => print!("{}", report),
+    }
+
+    Ok(())
+}
+
+/// The sidecar and tag file contents of an uploaded file, recorded before they are deleted.
+fn local_file_metadata(file: &Path, post_id: u32) -> serde_json::Value {
+    let txt_path = file.with_file_name(format!("{}.txt", file.file_name().unwrap_or_default().to_string_lossy()));
+
+    serde_json::json!({
+        "file": file.to_string_lossy(),
+        "post_id": post_id,
+        "sidecar": post_utils::read_sidecar(file),
+        "tags_txt": fs::read_to_string(txt_path).ok(),
+    })
+}
+
 fn delete_folder(path: &str) -> io::Result<()> {
     fs::remove_dir(path)
 }
@@ -263,34 +1638,69 @@ fn delete_file(path: &PathBuf) -> io::Result<()> {
     Ok(())
 }
 
-async fn upload_pool(client: &SzurubooruClient, path: &str) {
-    // match post_utils::get_sorted_filenames(path) {
-    //     Ok(filenames) => {
-    //         match upload_posts(client, path).await {
-    //             Ok(post_ids) => {
-    //                 // Create a new pool using the post IDs
-    //                 let pool_name = Path::new(path).file_name().unwrap().to_string_lossy().to_string();
-    //                 let create_pool = CreateUpdatePoolBuilder::default()
-    //                     .names(vec![pool_name])
-    //                     .posts(Some(post_ids))
-    //                     .build()
-    //                     .unwrap();
-
-    //                 match client.create_pool(&create_pool).await {
-    //                     Ok(_) => println!("Pool created successfully"),
-    //                     Err(e) => eprintln!("Error creating pool: {}", e),
-    //                 }
-    //             }
-    //             Err(e) => {
-    //                 eprintln!("Error uploading posts for pool: {}", e);
-    //             }
-    //         }
-    //     }
-    //     Err(e) => {
-    //         eprintln!("Error getting sorted filenames: {}", e);
-    //     }
-    // }
-    todo!()
+/// Uploads a folder in natural order and creates a pool named after the folder, or appends to
+/// the pool of that name if it exists. Files that already exist on the server are updated and
+/// keep their post id.
+async fn upload_pool(client: &SzurubooruClient, path: &str, config: Config) -> SzurubooruResult<u32> {
+    let files = post_utils::get_naturally_sorted_files(path)?;
+    let pool_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidInput, "Folder has no name")))?;
+
+    // Checked before uploading, files may be deleted as they are uploaded
+    if pool_utils::find_pool_by_name(client, &pool_name).await?.is_some() {
+        println!("Pool {} already exists, the posts will be added to its end.", pool_utils::pool_name(&pool_name));
+    }
+
+    let post_ids: Vec<u32> = upload_files(client, &files, &config)
+        .await?
+        .into_iter()
+        .map(|(_, post_id)| post_id)
+        .collect();
+    if post_ids.is_empty() {
+        return Err(SzurubooruClientError::IOError(Error::new(
+            ErrorKind::Other,
+            "No files were uploaded, no pool was created.",
+        )));
+    }
+    if post_ids.len() < files.len() {
+        eprintln!("{} of {} files failed to upload and are missing from the pool.", files.len() - post_ids.len(), files.len());
+    }
+
+    // Looked up again for a fresh version, the pool may have been edited during the upload
+    let pool = match pool_utils::find_pool_by_name(client, &pool_name).await? {
+        Some(existing) => {
+            let (posts, _) = pool_utils::arrange_posts(
+                &pool_utils::pool_post_ids(&existing),
+                &post_ids,
+                pool_utils::PoolUpdateMode::Append,
+            );
+            let pool = pool_utils::update_pool_posts(client, &existing, posts).await?;
+            println!("Extended pool {}", pool_utils::pool_name(&pool_name));
+            pool
+        }
+        None => {
+            let pool = pool_utils::create_pool(
+                client,
+                &pool_name,
+                config.pools.category.clone(),
+                pool_utils::unique_posts(post_ids),
+            )
+            .await?;
+            println!("Created pool {}", pool_utils::pool_name(&pool_name));
+            pool
+        }
+    };
+
+    if config.settings.delete_folder {
+        match delete_folder(path) {
+            Ok(_) => println!("Folder deleted successfully."),
+            Err(e) => eprintln!("Error deleting folder: {}", e),
+        }
+    }
+
+    Ok(pool.id.unwrap_or_default())
 }
 
 #[derive(Deserialize, Debug)]
@@ -298,6 +1708,14 @@ struct Config {
     server: ServerConfig,
     auth: AuthConfig,
     settings: SettingsConfig,
+    #[serde(default)]
+    danbooru: DanbooruConfig,
+    #[serde(default)]
+    tag_categories: Vec<TagCategoryConfig>,
+    #[serde(default)]
+    pools: PoolConfig,
+    #[serde(default)]
+    duplicates: DuplicatesConfig,
 }
 
 #[derive(Deserialize, Debug)]
@@ -319,6 +1737,48 @@ struct SettingsConfig {
     skip_on_error: bool,
     delete_files_in_progress: bool,
     delete_folder: bool,
+    audit_dir: Option<String>, // Snapshots of metadata before destructive commands, "audit" when unset
+    conflict_retries: Option<u8>, // Retries when a post is edited mid-update, 3 when unset
+}
+
+impl SettingsConfig {
+    fn audit_dir(&self) -> &str {
+        self.audit_dir.as_deref().unwrap_or("audit")
+    }
+
+    fn conflict_retries(&self) -> u8 {
+        self.conflict_retries.unwrap_or(3)
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct DanbooruConfig {
+    #[serde(default)]
+    min_usage: u32,
+    #[serde(default)]
+    categories: HashMap<String, String>, // Dump category number to local category name
+}
+
+#[derive(Deserialize, Debug)]
+struct TagCategoryConfig {
+    name: String,
+    color: Option<String>,
+    order: Option<u32>,
+    #[serde(default)]
+    default: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PoolConfig {
+    category: Option<String>, // Server default category when unset
+    group_key: Option<String>, // Sidecar key shared by the files of one work, "id" when unset
+    page_key: Option<String>, // Sidecar key with the page number, "num" when unset
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct DuplicatesConfig {
+    threshold: Option<f64>, // Minimum reverse search similarity (1 - distance), 0.85 when unset
+    keep: Option<String>, // Comma separated keeper rules, "lowest-id" when unset
 }
 
 fn load_or_create_config() -> Result<Config, Box<dyn std::error::Error>> {
@@ -335,7 +1795,7 @@ fn load_or_create_config() -> Result<Config, Box<dyn std::error::Error>> {
         // Accept "yes" or "y" (case-insensitive)
         if input.trim().eq_ignore_ascii_case("yes") || input.trim().eq_ignore_ascii_case("y") {
             // Default configuration
-            let default_config = r#"
+            let default_config = r##"
 [server]
 url = "https://your-server-url.com"
 
@@ -350,7 +1810,54 @@ skip_on_error = false
 log_level = "info"
 delete_files_in_progress = true
 delete_folder = false
-"#;
+audit_dir = "audit"
+conflict_retries = 3
+
+[pools]
+category = "default"
+group_key = "id"
+page_key = "num"
+
+[duplicates]
+threshold = 0.85
+keep = "highest-resolution,lowest-id"
+
+[danbooru]
+min_usage = 100
+
+[danbooru.categories]
+0 = "default"
+1 = "artist"
+3 = "copyright"
+4 = "character"
+5 = "meta"
+
+[[tag_categories]]
+name = "default"
+color = "default"
+order = 1
+default = true
+
+[[tag_categories]]
+name = "artist"
+color = "#A00"
+order = 2
+
+[[tag_categories]]
+name = "copyright"
+color = "#A0A"
+order = 3
+
+[[tag_categories]]
+name = "character"
+color = "#0A0"
+order = 4
+
+[[tag_categories]]
+name = "meta"
+color = "#F80"
+order = 5
+"##;
 
             // Write default config to file
             fs::write(config_path, default_config)?;
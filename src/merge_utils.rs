@@ -0,0 +1,307 @@
+// merge_utils.rs
+
+use crate::post_utils::MergePair;
+use models::PostResource;
+use std::collections::HashMap;
+use std::fmt;
+use szurubooru_client::*;
+
+pub struct ResolvedPairs {
+    /// Pairs pointing at their final surviving post, deepest chain links first.
+    pub pairs: Vec<MergePair>,
+    /// Chains that were collapsed, as `(removed post, chain)`.
+    pub collapsed: Vec<(u32, Vec<u32>)>,
+    /// Cycles and contradictions, the posts involved are left out of `pairs`.
+    pub problems: Vec<String>,
+}
+
+enum Problem {
+    Cycle(Vec<u32>),
+    Conflict(u32, Vec<u32>),
+}
+
+/// Treats the pairs as a graph of `removed -> target` edges and points every removed post at the
+/// post that survives at the end of its chain, so `A -> B, B -> C` becomes `A -> C, B -> C`.
+pub fn resolve_merge_pairs(pairs: Vec<MergePair>) -> ResolvedPairs {
+    let mut edges: HashMap<u32, Vec<u32>> = HashMap::new();
+    let mut order: Vec<&MergePair> = Vec::new();
+    for pair in &pairs {
+        let targets = edges.entry(pair.remove_post).or_default();
+        if !targets.contains(&pair.merge_to_post) {
+            targets.push(pair.merge_to_post);
+        }
+        if !order.iter().any(|seen| seen.remove_post == pair.remove_post) {
+            order.push(pair);
+        }
+    }
+
+    let mut resolved = Vec::new();
+    let mut collapsed = Vec::new();
+    let mut problems = Vec::new();
+
+    for pair in order {
+        let mut path = Vec::new();
+        match find_root(pair.remove_post, &edges, &mut path) {
+            Ok((root, chain)) => {
+                if chain.len() > 2 {
+                    collapsed.push((pair.remove_post, chain.clone()));
+                }
+                let merge_pair = MergePair {
+                    remove_post: pair.remove_post,
+                    merge_to_post: root,
+                    replace: pair.replace,
+                };
+                resolved.push((chain.len(), merge_pair));
+            }
+            Err(problem) => {
+                let message = match problem {
+                    Problem::Cycle(cycle) => format!(
+                        "Post {} leads into a merge cycle: {}",
+                        pair.remove_post,
+                        join_ids(&cycle, " -> ")
+                    ),
+                    Problem::Conflict(post, roots) => format!(
+                        "Post {} would end up merged into different posts: {}",
+                        post,
+                        join_ids(&roots, ", ")
+                    ),
+                };
+                // Every post upstream of a conflict hits the same one
+                if !problems.contains(&message) {
+                    problems.push(message);
+                }
+            }
+        }
+    }
+
+    // Longer chains first, so every link is merged before the post it pointed at
+    resolved.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    ResolvedPairs {
+        pairs: resolved.into_iter().map(|(_, pair)| pair).collect(),
+        collapsed,
+        problems,
+    }
+}
+
+/// Follows `node` to the post that is never removed, returning it with the chain that led there.
+fn find_root(node: u32, edges: &HashMap<u32, Vec<u32>>, path: &mut Vec<u32>) -> Result<(u32, Vec<u32>), Problem> {
+    if let Some(position) = path.iter().position(|visited| *visited == node) {
+        let mut cycle = path[position..].to_vec();
+        cycle.push(node);
+        return Err(Problem::Cycle(cycle));
+    }
+
+    let Some(targets) = edges.get(&node) else {
+        return Ok((node, vec![node]));
+    };
+
+    path.push(node);
+    let mut found: Option<(u32, Vec<u32>)> = None;
+    let mut roots = Vec::new();
+    for target in targets {
+        let (root, chain) = find_root(*target, edges, path)?;
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+        // Keep the longest chain to the root for ordering
+        if found.as_ref().is_none_or(|(_, longest)| chain.len() > longest.len()) {
+            found = Some((root, chain));
+        }
+    }
+    path.pop();
+
+    if roots.len() > 1 {
+        return Err(Problem::Conflict(node, roots));
+    }
+
+    let (root, chain) = found.expect("every node in edges has a target");
+    let mut full_chain = vec![node];
+    full_chain.extend(chain);
+    Ok((root, full_chain))
+}
+
+fn join_ids(ids: &[u32], separator: &str) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(separator)
+}
+
+/// What merging `removed` into `target` will change on the target.
+pub struct MergePreview {
+    pub remove_post: u32,
+    pub merge_to_post: u32,
+    pub changes: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl fmt::Display for MergePreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.remove_post, self.merge_to_post)?;
+        for warning in &self.warnings {
+            write!(f, "\n    ! {}", warning)?;
+        }
+        for change in &self.changes {
+            write!(f, "\n    {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tags, relations and pools of the removed post carry over to the target,
+/// while its sources and safety rating are lost.
+pub fn preview_merge(removed: &PostResource, target: &PostResource, replace_content: bool) -> MergePreview {
+    let tag_names = |post: &PostResource| -> Vec<String> {
+        post.tags
+            .iter()
+            .flatten()
+            .filter_map(|tag| tag.names.first().cloned())
+            .collect()
+    };
+    let relations = |post: &PostResource| -> Vec<String> {
+        post.relations.iter().flatten().map(|relation| relation.id.to_string()).collect()
+    };
+    let pools = |post: &PostResource| -> Vec<String> {
+        post.pools
+            .iter()
+            .flatten()
+            .map(|pool| {
+                let name = pool.names.as_ref().and_then(|names| names.first().cloned()).unwrap_or_default();
+                format!("{} ({})", pool.id.unwrap_or_default(), name)
+            })
+            .collect()
+    };
+    let sources = |post: &PostResource| -> Vec<String> {
+        post.source.iter().flat_map(|source| source.lines()).map(String::from).collect()
+    };
+
+    let target_id = target.id.unwrap_or_default().to_string();
+    let mut changes = Vec::new();
+    for (label, sign, items) in [
+        ("tags", "+", only_in(&tag_names(removed), &tag_names(target))),
+        ("relations", "+", only_in(&relations(removed), &relations(target))),
+        ("pools", "+", only_in(&pools(removed), &pools(target))),
+        ("sources", "-", only_in(&sources(removed), &sources(target))),
+    ] {
+        let items: Vec<String> = items.into_iter().filter(|item| *item != target_id).collect();
+        if !items.is_empty() {
+            changes.push(format!("{}: {}{}", label, sign, items.join(&format!(" {}", sign))));
+        }
+    }
+    if replace_content {
+        changes.push(format!("content: {} -> {}", dimensions(target), dimensions(removed)));
+    }
+
+    let mut warnings = Vec::new();
+    if let (Some(removed_safety), Some(target_safety)) = (&removed.safety, &target.safety) {
+        if removed_safety != target_safety {
+            warnings.push(format!("safety differs: {:?} vs {:?}", removed_safety, target_safety));
+        }
+    }
+    if dimensions_differ(removed, target) {
+        warnings.push(format!("dimensions differ: {} vs {}", dimensions(removed), dimensions(target)));
+    }
+
+    MergePreview {
+        remove_post: removed.id.unwrap_or_default(),
+        merge_to_post: target.id.unwrap_or_default(),
+        changes,
+        warnings,
+    }
+}
+
+fn only_in(items: &[String], other: &[String]) -> Vec<String> {
+    items.iter().filter(|item| !other.contains(item)).cloned().collect()
+}
+
+fn dimensions(post: &PostResource) -> String {
+    format!("{}x{}", post.canvas_width.unwrap_or(0), post.canvas_height.unwrap_or(0))
+}
+
+/// Aspect ratios more than 5% apart or one post having over four times the pixels of the other.
+fn dimensions_differ(a: &PostResource, b: &PostResource) -> bool {
+    let size = |post: &PostResource| {
+        (post.canvas_width.unwrap_or(0) as f64, post.canvas_height.unwrap_or(0) as f64)
+    };
+    let ((a_width, a_height), (b_width, b_height)) = (size(a), size(b));
+    if a_width == 0.0 || a_height == 0.0 || b_width == 0.0 || b_height == 0.0 {
+        return false;
+    }
+
+    let aspect_ratio = (a_width / a_height) / (b_width / b_height);
+    let pixel_ratio = (a_width * a_height) / (b_width * b_height);
+    !(0.95..=1.05).contains(&aspect_ratio) || !(0.25..=4.0).contains(&pixel_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(remove_post: u32, merge_to_post: u32) -> MergePair {
+        MergePair { remove_post, merge_to_post, replace: None }
+    }
+
+    fn targets(resolved: &ResolvedPairs) -> Vec<(u32, u32)> {
+        resolved.pairs.iter().map(|pair| (pair.remove_post, pair.merge_to_post)).collect()
+    }
+
+    #[test]
+    fn chain_collapses_to_last_post() {
+        let resolved = resolve_merge_pairs(vec![pair(1, 2), pair(2, 3)]);
+
+        assert_eq!(targets(&resolved), vec![(1, 3), (2, 3)]);
+        assert_eq!(resolved.collapsed, vec![(1, vec![1, 2, 3])]);
+        assert!(resolved.problems.is_empty());
+    }
+
+    #[test]
+    fn fan_in_keeps_every_pair() {
+        let resolved = resolve_merge_pairs(vec![pair(1, 3), pair(2, 3), pair(4, 3)]);
+
+        assert_eq!(targets(&resolved), vec![(1, 3), (2, 3), (4, 3)]);
+        assert!(resolved.collapsed.is_empty());
+        assert!(resolved.problems.is_empty());
+    }
+
+    #[test]
+    fn two_node_cycle_is_reported() {
+        let resolved = resolve_merge_pairs(vec![pair(1, 2), pair(2, 1)]);
+
+        assert!(resolved.pairs.is_empty());
+        assert_eq!(resolved.problems.len(), 2);
+        assert!(resolved.problems.iter().all(|problem| problem.contains("cycle")));
+    }
+
+    #[test]
+    fn post_with_two_targets_is_reported() {
+        let resolved = resolve_merge_pairs(vec![pair(1, 2), pair(1, 3), pair(4, 5)]);
+
+        assert_eq!(targets(&resolved), vec![(4, 5)]);
+        assert_eq!(resolved.problems, vec!["Post 1 would end up merged into different posts: 2, 3".to_string()]);
+    }
+
+    fn read_pairs(name: &str, content: &str) -> Result<Vec<(u32, u32)>, String> {
+        let path = std::env::temp_dir().join(format!("oxibooru_toolkit_{}_{}.txt", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let pairs = crate::post_utils::read_merge_pairs(&path.to_string_lossy());
+        std::fs::remove_file(&path).unwrap();
+        pairs
+            .map(|pairs| pairs.iter().map(|pair| (pair.remove_post, pair.merge_to_post)).collect())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn first_pair_with_replace_column_is_not_a_header() {
+        assert_eq!(read_pairs("replace", "12 34 replace\n56 78\n"), Ok(vec![(12, 34), (56, 78)]));
+        assert_eq!(read_pairs("keep", "12 34 keep\n56 78 auto\n"), Ok(vec![(12, 34), (56, 78)]));
+    }
+
+    #[test]
+    fn header_names_the_columns() {
+        assert_eq!(read_pairs("header", "merge_to,remove,replace\n34,12,auto\n"), Ok(vec![(12, 34)]));
+    }
+
+    #[test]
+    fn invalid_first_line_is_reported() {
+        let error = read_pairs("typo", "12x 34\n56 78\n").unwrap_err();
+        assert!(error.contains("Line 1"), "{}", error);
+    }
+}
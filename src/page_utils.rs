@@ -0,0 +1,31 @@
+// page_utils.rs
+
+use models::PagedSearchResult;
+use std::future::Future;
+use szurubooru_client::*;
+
+const PAGE_SIZE: u32 = 100;
+
+/// Pages through a list endpoint until every result is fetched. `fetch_page` receives a request
+/// already limited and offset to the next page, e.g. `|request| async move { request.list_tags(Some(query)).await }`.
+pub async fn fetch_all<'a, T, F, Fut>(client: &'a SzurubooruClient, mut fetch_page: F) -> SzurubooruResult<Vec<T>>
+where
+    F: FnMut(SzurubooruRequest<'a>) -> Fut,
+    Fut: Future<Output = SzurubooruResult<PagedSearchResult<T>>>,
+{
+    let mut results = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        let page = fetch_page(client.request().with_limit(PAGE_SIZE).with_offset(offset)).await?;
+        let count = page.results.len() as u32;
+        results.extend(page.results);
+        offset += count;
+
+        if count == 0 || offset >= page.total {
+            break;
+        }
+    }
+
+    Ok(results)
+}
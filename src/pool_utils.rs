@@ -0,0 +1,241 @@
+// pool_utils.rs
+
+use crate::page_utils::fetch_all;
+use models::{CreateUpdatePool, PoolResource};
+use serde_json::Value;
+use szurubooru_client::tokens::QueryToken;
+use szurubooru_client::*;
+
+const PAGES_PREFIX: &str = "pages: ";
+
+/// A `(page, post id)` pair, the page is unknown for files without a page number.
+pub type PoolEntry = (Option<u64>, u32);
+
+/// Pool names cannot contain whitespace, so it is replaced the same way as in tags.
+pub fn pool_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+pub async fn create_pool(
+    client: &SzurubooruClient,
+    name: &str,
+    category: Option<String>,
+    posts: Vec<u32>,
+) -> SzurubooruResult<PoolResource> {
+    let pool = CreateUpdatePool {
+        version: None,
+        names: Some(vec![pool_name(name)]),
+        category,
+        description: None,
+        posts: Some(posts),
+    };
+
+    client.request().create_pool(&pool).await
+}
+
+/// Removes repeated post ids, keeping the first position of each.
+pub fn unique_posts(posts: Vec<u32>) -> Vec<u32> {
+    let mut unique = Vec::with_capacity(posts.len());
+    for post in posts {
+        if !unique.contains(&post) {
+            unique.push(post);
+        }
+    }
+    unique
+}
+
+/// Finds a pool by one of its names.
+pub async fn find_pool_by_name(client: &SzurubooruClient, name: &str) -> SzurubooruResult<Option<PoolResource>> {
+    let name = pool_name(name);
+    let query = vec![QueryToken::token("name", &name)];
+    let pools = client.request().list_pools(Some(&query)).await?;
+
+    Ok(pools.results.into_iter().find(|pool| {
+        pool.names
+            .as_ref()
+            .is_some_and(|names| names.iter().any(|pool_name| pool_name.eq_ignore_ascii_case(&name)))
+    }))
+}
+
+/// Source page numbers are kept in the pool description as a `pages: 1 2 3` line,
+/// one number per post in pool order.
+pub fn parse_pages(description: &str) -> Option<Vec<u64>> {
+    let line = description.lines().find_map(|line| line.trim().strip_prefix(PAGES_PREFIX))?;
+    line.split_whitespace().map(|page| page.parse().ok()).collect()
+}
+
+fn with_pages(description: &str, pages: &[u64]) -> String {
+    let mut lines: Vec<String> = description
+        .lines()
+        .filter(|line| !line.trim().starts_with(PAGES_PREFIX))
+        .map(String::from)
+        .collect();
+    lines.push(format!(
+        "{}{}",
+        PAGES_PREFIX,
+        pages.iter().map(|page| page.to_string()).collect::<Vec<_>>().join(" ")
+    ));
+    lines.join("\n")
+}
+
+/// Creates the pool `name` from `(page, post id)` entries, or adds the entries to it if it exists.
+/// Posts are ordered by page when every page number is known, otherwise new posts go at the end.
+pub async fn create_or_extend_pool(
+    client: &SzurubooruClient,
+    name: &str,
+    category: Option<String>,
+    description: &str,
+    mut entries: Vec<PoolEntry>,
+) -> SzurubooruResult<PoolResource> {
+    entries.sort_by_key(|(page, _)| page.unwrap_or(u64::MAX));
+
+    let existing = find_pool_by_name(client, name).await?;
+    let (version, mut combined, description) = match &existing {
+        Some(pool) => {
+            let posts = pool_post_ids(pool);
+            let description = pool.description.clone().unwrap_or_default();
+            let pages = parse_pages(&description).filter(|pages| pages.len() == posts.len());
+            let combined: Vec<PoolEntry> = match pages {
+                Some(pages) => pages.into_iter().map(Some).zip(posts).collect(),
+                None => posts.into_iter().map(|post| (None, post)).collect(),
+            };
+            (pool.version, combined, description)
+        }
+        None => (None, Vec::new(), description.to_string()),
+    };
+
+    for (page, post) in entries {
+        if !combined.iter().any(|(_, existing_post)| *existing_post == post) {
+            combined.push((page, post));
+        }
+    }
+
+    let description = if combined.iter().all(|(page, _)| page.is_some()) {
+        combined.sort_by_key(|(page, _)| *page);
+        let pages: Vec<u64> = combined.iter().filter_map(|(page, _)| *page).collect();
+        with_pages(&description, &pages)
+    } else {
+        description
+    };
+
+    // Names and category of an existing pool may have been changed by hand, they are left alone
+    let pool = CreateUpdatePool {
+        version,
+        names: existing.is_none().then(|| vec![pool_name(name)]),
+        category: category.filter(|_| existing.is_none()),
+        description: Some(description),
+        posts: Some(combined.into_iter().map(|(_, post)| post).collect()),
+    };
+
+    match existing.and_then(|pool| pool.id) {
+        Some(pool_id) => client.request().update_pool(pool_id, &pool).await,
+        None => client.request().create_pool(&pool).await,
+    }
+}
+
+/// Pool membership read from a sidecar: pixiv works share an `id` and number pages with `num`,
+/// so `pixiv_12345` is the pool for every page of work 12345.
+#[derive(Debug, Clone)]
+pub struct SidecarGroup {
+    pub pool_name: String,
+    pub description: String,
+    pub page: Option<u64>,
+}
+
+pub fn sidecar_group(sidecar: &Value, group_key: &str, page_key: &str) -> Option<SidecarGroup> {
+    let group = match sidecar.get(group_key)? {
+        Value::String(group) => group.clone(),
+        Value::Null => return None,
+        group => group.to_string(),
+    };
+    let site = sidecar.get("category").and_then(|site| site.as_str()).unwrap_or("group");
+    let page = sidecar
+        .get(page_key)
+        .and_then(|page| page.as_u64().or_else(|| page.as_str()?.parse().ok()));
+
+    Some(SidecarGroup {
+        pool_name: pool_name(&format!("{}_{}", site, group)),
+        description: format!("{} {}: {}", site, group_key, group),
+        page,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoolUpdateMode {
+    Append,
+    Prepend,
+    /// Listed posts move to the front in the listed order, the rest keep their order behind them.
+    Reorder,
+}
+
+impl PoolUpdateMode {
+    pub fn parse(mode: &str) -> Option<PoolUpdateMode> {
+        match mode {
+            "append" => Some(PoolUpdateMode::Append),
+            "prepend" => Some(PoolUpdateMode::Prepend),
+            "reorder" => Some(PoolUpdateMode::Reorder),
+            _ => None,
+        }
+    }
+}
+
+/// Combines the current pool posts with `posts`. Posts already in the pool are never duplicated,
+/// and on reorder posts that are not in the pool are returned as ignored.
+pub fn arrange_posts(current: &[u32], posts: &[u32], mode: PoolUpdateMode) -> (Vec<u32>, Vec<u32>) {
+    let posts = unique_posts(posts.to_vec());
+    let rest = |listed: &[u32]| -> Vec<u32> { current.iter().filter(|post| !listed.contains(post)).copied().collect() };
+
+    match mode {
+        PoolUpdateMode::Append => {
+            let mut arranged = current.to_vec();
+            arranged.extend(posts.iter().filter(|post| !current.contains(post)));
+            (arranged, Vec::new())
+        }
+        PoolUpdateMode::Prepend => {
+            let mut arranged = posts.clone();
+            arranged.extend(rest(&posts));
+            (arranged, Vec::new())
+        }
+        PoolUpdateMode::Reorder => {
+            let (listed, ignored): (Vec<u32>, Vec<u32>) = posts.into_iter().partition(|post| current.contains(post));
+            let mut arranged = listed.clone();
+            arranged.extend(rest(&listed));
+            (arranged, ignored)
+        }
+    }
+}
+
+/// Rewrites the post list of a pool, using the version it was read with.
+pub async fn update_pool_posts(
+    client: &SzurubooruClient,
+    pool: &PoolResource,
+    posts: Vec<u32>,
+) -> SzurubooruResult<PoolResource> {
+    let update = CreateUpdatePool {
+        version: pool.version,
+        names: None,
+        category: None,
+        description: None,
+        posts: Some(posts),
+    };
+
+    client.request().update_pool(pool.id.unwrap_or_default(), &update).await
+}
+
+pub fn pool_post_ids(pool: &PoolResource) -> Vec<u32> {
+    pool.posts.iter().flatten().map(|post| post.id).collect()
+}
+
+/// Pages through every pool on the server.
+pub async fn fetch_pools(client: &SzurubooruClient) -> SzurubooruResult<Vec<PoolResource>> {
+    let query = &Vec::new();
+    fetch_all(client, |request| async move { request.list_pools(Some(query)).await }).await
+}
+
+/// Page numbers between the first and last recorded page that are not in the pool.
+pub fn missing_pages(pages: &[u64]) -> Vec<u64> {
+    let (Some(first), Some(last)) = (pages.iter().min(), pages.iter().max()) else {
+        return Vec::new();
+    };
+    (*first..=*last).filter(|page| !pages.contains(page)).collect()
+}
@@ -1,22 +1,32 @@
 // post_utils.rs
 
+use crate::csv_utils::parse_csv_line;
+use crate::edit_utils::empty_update;
+use crate::error_utils::{is_not_found, is_version_conflict};
+use crate::page_utils::fetch_all;
 use errors::SzurubooruClientError;
-use models::{CreateUpdatePost, PostSafety};
+use models::{CreateUpdatePost, PostResource, PostSafety};
 use serde_json::Value;
 use tokio::fs::File;
 use tokio::io::BufReader;
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::hash::Hash;
 use std::io::{Read, self, BufRead, Error, ErrorKind};
 use std::path::{Path, PathBuf};
+use szurubooru_client::tokens::QueryToken;
 use szurubooru_client::*;
 
 const MEDIA_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "mp4", "webm", "gif", "swf", "webp"];
 
+/// Uploads a file, or merges its metadata into the post that already has the exact same content.
+/// If that post is edited while we update it, it is refetched and the merge is redone against
+/// the fresh state up to `conflict_retries` times.
 pub async fn create_post(
     client: &SzurubooruClient,
     file_path: &PathBuf,
+    conflict_retries: u8,
 ) -> SzurubooruResult<(u32, Option<String>)> {
     let search_result = client
         .request()
@@ -37,47 +47,22 @@ pub async fn create_post(
             .collect();
         post.relations = Some(similar_posts_ids);
     }
-    if exact_post.is_some() {
-        let exact_post = exact_post.unwrap();
-        let exact_tags: Option<Vec<String>> = exact_post.tags.map(|tags_vec| {
-            tags_vec
-                .into_iter()
-                .filter_map(|tag_resource| tag_resource.names.first().cloned())
-                .collect()
-        });
-        let exact_relations: Option<Vec<u32>> = exact_post.relations.map(|tags_vec| {
-            tags_vec
-                .into_iter()
-                .filter_map(|post_resource| Some(post_resource.id))
-                .collect()
-        });
-
-        post = CreateUpdatePost {
-            version: exact_post.version,
-            tags: merge_vecs_unique(&exact_tags, &post.tags),
-            safety: if post.safety.is_some() {
-                post.safety
-            } else if exact_post.safety.is_some() {
-                exact_post.safety
-            } else {
-                Some(PostSafety::Unsafe)
-            },
-            source: merge_source(exact_post.source, post.source),
-            relations: merge_vecs_unique(&exact_relations, &post.relations),
-            notes: None,
-            flags: None,
-            content_url: None,
-            content_token: None,
-            anonymous: Some(false),
-        };
-        return match client
-            .request()
-            .update_post(exact_post.id.unwrap(), &post)
-            .await
-        {
-            Ok(post) => Ok((post.id.unwrap(), artist)),
-            Err(e) => Err(e),
-        };
+    if let Some(mut exact_post) = exact_post {
+        let exact_id = exact_post.id.unwrap();
+        let mut attempts = 0;
+
+        loop {
+            let update = merge_into_existing(&exact_post, &post);
+            match client.request().update_post(exact_id, &update).await {
+                Ok(post) => return Ok((post.id.unwrap(), artist)),
+                Err(e) if is_version_conflict(&e) && attempts < conflict_retries => {
+                    attempts += 1;
+                    println!("Post {} was edited during the update, retrying ({}/{})", exact_id, attempts, conflict_retries);
+                    exact_post = client.request().get_post(exact_id).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 
     match client
@@ -90,6 +75,96 @@ pub async fn create_post(
     }
 }
 
+/// Replaces the content of `post_id` with `file_path`, and its thumbnail if one is given.
+/// Only the content is sent, so tags, relations and everything else stay as they are.
+pub async fn replace_content(
+    client: &SzurubooruClient,
+    post_id: u32,
+    file_path: &Path,
+    thumbnail: Option<&Path>,
+    conflict_retries: u8,
+) -> SzurubooruResult<PostResource> {
+    let file_token = client
+        .request()
+        .upload_temporary_file_from_path(file_path)
+        .await?;
+    let mut post = client.request().get_post(post_id).await?;
+    let mut attempts = 0;
+
+    loop {
+        let mut update = empty_update(&post);
+        update.content_token = Some(file_token.token.clone());
+        match client.request().update_post_from_token(post_id, &update).await {
+            Ok(updated) => {
+                post = updated;
+                break;
+            }
+            Err(e) if is_version_conflict(&e) && attempts < conflict_retries => {
+                attempts += 1;
+                println!("Post {} was edited during the update, retrying ({}/{})", post_id, attempts, conflict_retries);
+                post = client.request().get_post(post_id).await?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if let Some(thumbnail) = thumbnail {
+        loop {
+            match client
+                .request()
+                .update_post_from_file_path(post_id, Option::<PathBuf>::None, Some(thumbnail), &empty_update(&post))
+                .await
+            {
+                Ok(updated) => return Ok(updated),
+                Err(e) if is_version_conflict(&e) && attempts < conflict_retries => {
+                    attempts += 1;
+                    println!("Post {} was edited during the update, retrying ({}/{})", post_id, attempts, conflict_retries);
+                    post = client.request().get_post(post_id).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(post)
+}
+
+/// The update for `existing` that adds the tags, sources and relations of `post`,
+/// using the version `existing` was read with.
+fn merge_into_existing(existing: &PostResource, post: &CreateUpdatePost) -> CreateUpdatePost {
+    let existing_tags: Option<Vec<String>> = existing.tags.as_ref().map(|tags_vec| {
+        tags_vec
+            .iter()
+            .filter_map(|tag_resource| tag_resource.names.first().cloned())
+            .collect()
+    });
+    let existing_relations: Option<Vec<u32>> = existing.relations.as_ref().map(|relations_vec| {
+        relations_vec
+            .iter()
+            .map(|post_resource| post_resource.id)
+            .collect()
+    });
+
+    CreateUpdatePost {
+        version: existing.version,
+        tags: merge_vecs_unique(&existing_tags, &post.tags),
+        safety: if post.safety.is_some() {
+            post.safety.clone()
+        } else if existing.safety.is_some() {
+            existing.safety.clone()
+        } else {
+            Some(PostSafety::Unsafe)
+        },
+        source: merge_source(existing.source.clone(), post.source.clone()),
+        relations: merge_vecs_unique(&existing_relations, &post.relations),
+        notes: None,
+        flags: None,
+        content_url: None,
+        content_token: None,
+        anonymous: Some(false),
+    }
+}
+
 fn make_post_with_metadata(
     token: String,
     file_path: PathBuf,
@@ -165,7 +240,7 @@ fn make_post_with_metadata(
         println!("Website: {}",website.to_string());
 
         let tags_vec: Option<Vec<String>> = match website {
-            "art.mobius.social" | "sankaku" | "danbooru" => {
+            "art.mobius.social" | "sankaku" | "danbooru" | "oxibooru" => {
                 // Handle tags as an array
                 json_data.get("tags").and_then(|tags| tags.as_array()).map(|tags_array| {
                     tags_array
@@ -217,15 +292,10 @@ fn make_post_with_metadata(
             .and_then(|s| s.as_str())
             .or_else(|| json_data.get("rating").and_then(|r| r.as_str()))
         {
-            post.safety = match safety_str.to_lowercase().as_str() {
-                "safe" | "s" => Some(PostSafety::Safe),
-                "sketchy" | "questionable" | "q" => Some(PostSafety::Sketchy),
-                "unsafe" | "explicit" | "e" => Some(PostSafety::Unsafe),
-                other => {
-                    println!("Unrecognized safety/rating found: {}", other);
-                    None
-                }
-            };
+            post.safety = parse_safety(safety_str);
+            if post.safety.is_none() {
+                println!("Unrecognized safety/rating found: {}", safety_str.to_lowercase());
+            }
         }
     }
 
@@ -236,22 +306,38 @@ fn make_post_with_metadata(
     Ok((post, artist))
 }
 
-fn merge_source(opt1: Option<String>, opt2: Option<String>) -> Option<String> {
-    let mut unique_lines = HashSet::new();
+/// Reads the gallery-dl `<file>.json` sidecar next to a media file, if there is one.
+pub fn read_sidecar(file_path: &Path) -> Option<Value> {
+    let json_file_name = format!("{}.json", file_path.file_name()?.to_string_lossy());
+    let content = fs::read_to_string(file_path.with_file_name(json_file_name)).ok()?;
+    serde_json::from_str(&content).ok()
+}
 
-    // Collect lines from both options, if they exist
-    if let Some(s1) = opt1 {
-        unique_lines.extend(s1.lines().map(String::from));
+pub fn parse_safety(safety: &str) -> Option<PostSafety> {
+    match safety.to_lowercase().as_str() {
+        "safe" | "s" => Some(PostSafety::Safe),
+        "sketchy" | "questionable" | "q" => Some(PostSafety::Sketchy),
+        "unsafe" | "explicit" | "e" => Some(PostSafety::Unsafe),
+        _ => None,
     }
-    if let Some(s2) = opt2 {
-        unique_lines.extend(s2.lines().map(String::from));
+}
+
+pub fn merge_source(opt1: Option<String>, opt2: Option<String>) -> Option<String> {
+    let mut seen = HashSet::new();
+    let mut unique_lines = Vec::new();
+
+    // Collect lines from both options in order, if they exist
+    for line in opt1.iter().chain(opt2.iter()).flat_map(|s| s.lines()) {
+        if seen.insert(line) {
+            unique_lines.push(line);
+        }
     }
 
     // If there are any unique lines, join them with newline; otherwise, return None
     if unique_lines.is_empty() {
         None
     } else {
-        Some(unique_lines.into_iter().collect::<Vec<_>>().join("\n"))
+        Some(unique_lines.join("\n"))
     }
 }
 
@@ -286,6 +372,48 @@ pub fn get_sorted_filenames(path: &str) -> SzurubooruResult<Vec<String>> {
         .collect())
 }
 
+/// Files sorted the way a person would read them, so `page2` comes before `page10`.
+pub fn get_naturally_sorted_files(path: &str) -> SzurubooruResult<Vec<PathBuf>> {
+    let mut files = get_files(path)?;
+    files.sort_by(|a, b| natural_cmp(&a.file_name().unwrap().to_string_lossy(), &b.file_name().unwrap().to_string_lossy()));
+    Ok(files)
+}
+
+/// Compares runs of digits by their numeric value and everything else case-insensitively.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(c);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (take_number(&mut a_chars), take_number(&mut b_chars));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
 pub fn get_files(path: &str) -> Result<Vec<PathBuf>, SzurubooruClientError> {
     let mut post_files = Vec::new();
     let dir = Path::new(path);
@@ -312,37 +440,273 @@ pub fn get_files(path: &str) -> Result<Vec<PathBuf>, SzurubooruClientError> {
     Ok(post_files)
 }
 
-pub fn read_number_pairs(file_path: &str) -> Result<Vec<(u32, u32)>, SzurubooruClientError> {
-    let mut number_pairs = Vec::new();
-    let path = Path::new(file_path);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaceContent {
+    Keep,
+    Replace,
+    /// Replace when the removed post is the better file of the same type.
+    Auto,
+}
 
-    if path.is_file() {
-        let file = fs::File::open(path).map_err(|e| SzurubooruClientError::IOError(e))?;
-        let reader = io::BufReader::new(file);
-
-        for line in reader.lines() {
-            let line = line.map_err(|e| SzurubooruClientError::IOError(e))?;
-            let numbers: Vec<&str> = line.split_whitespace().collect();
-
-            if numbers.len() == 2 {
-                let first = numbers[0].parse::<u32>().map_err(|_| {
-                    SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, "Failed to parse the first number."))
-                })?;
-                let second = numbers[1].parse::<u32>().map_err(|_| {
-                    SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, "Failed to parse the second number."))
-                })?;
-                number_pairs.push((first, second));
-            } else {
-                return Err(SzurubooruClientError::IOError(Error::new(
-                    ErrorKind::InvalidData,
-                    "Each line must contain exactly two numbers."
-                )));
-            }
+impl ReplaceContent {
+    pub fn parse(value: &str) -> Option<ReplaceContent> {
+        match value.to_lowercase().as_str() {
+            "keep" | "never" | "no" | "false" | "0" => Some(ReplaceContent::Keep),
+            "replace" | "always" | "yes" | "true" | "1" => Some(ReplaceContent::Replace),
+            "auto" => Some(ReplaceContent::Auto),
+            _ => None,
         }
-    } else {
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePair {
+    pub remove_post: u32,
+    pub merge_to_post: u32,
+    /// Per line override of the `--replace` mode.
+    pub replace: Option<ReplaceContent>,
+}
+
+impl ReplaceContent {
+    /// Whether merging `removed` into `target` should take over the removed post's content.
+    pub fn decide(self, removed: &PostResource, target: &PostResource) -> bool {
+        match self {
+            ReplaceContent::Keep => false,
+            ReplaceContent::Replace => true,
+            ReplaceContent::Auto => removed_is_better(removed, target),
+        }
+    }
+}
+
+/// True when `removed` has the same type as `target` and more pixels, or as many pixels and a larger file.
+pub fn removed_is_better(removed: &PostResource, target: &PostResource) -> bool {
+    if removed.mime_type.is_none() || removed.mime_type != target.mime_type {
+        return false;
+    }
+
+    let pixels = |post: &PostResource| {
+        post.canvas_width.unwrap_or(0) as u64 * post.canvas_height.unwrap_or(0) as u64
+    };
+    let file_size = |post: &PostResource| post.file_size.unwrap_or(0);
+
+    pixels(removed) > pixels(target) || (pixels(removed) == pixels(target) && file_size(removed) > file_size(target))
+}
+
+const REMOVE_COLUMNS: &[&str] = &["remove", "removed", "remove_post", "duplicate"];
+const MERGE_TO_COLUMNS: &[&str] = &["merge_to", "merge_to_post", "target"];
+const REPLACE_COLUMNS: &[&str] = &["replace", "replace_content", "replace_post_content"];
+
+/// Reads `remove_post merge_to_post [keep|replace|auto]` lines separated by whitespace, commas or tabs.
+///
+/// Blank lines and `#` comments are skipped, and a CSV/TSV header naming the columns is recognised.
+/// Every invalid line is reported with its line number, self-pairs are rejected and
+/// repeated pairs are dropped with a warning.
+pub fn read_merge_pairs(file_path: &str) -> Result<Vec<MergePair>, SzurubooruClientError> {
+    let path = Path::new(file_path);
+    if !path.is_file() {
         let dir_error: std::io::Error = Error::new(std::io::ErrorKind::Other, "Provided path is not a file");
         return Err(SzurubooruClientError::IOError(dir_error));
     }
 
-    Ok(number_pairs)
+    let file = fs::File::open(path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut merge_pairs: Vec<(usize, MergePair)> = Vec::new();
+    let mut errors = Vec::new();
+    let mut columns = (0, 1, 2);
+    let mut first_line = true;
+
+    for (index, line) in io::BufReader::new(file).lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.map_err(|e| SzurubooruClientError::IOError(e))?;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<String> = if line.contains('\t') {
+            parse_csv_line(line, '\t')
+        } else if line.contains(',') {
+            parse_csv_line(line, ',')
+        } else {
+            line.split_whitespace().map(String::from).collect()
+        };
+        let fields: Vec<&str> = fields.iter().map(|field| field.trim()).collect();
+
+        // Only a first line made up entirely of known column names is a header, anything else is
+        // parsed as a pair, so `12 34 replace` stays a pair and a typo is reported
+        let is_column = |field: &str| {
+            [REMOVE_COLUMNS, MERGE_TO_COLUMNS, REPLACE_COLUMNS].iter().any(|names| names.contains(&field.to_lowercase().as_str()))
+        };
+        let position = |names: &[&str]| fields.iter().position(|field| names.contains(&field.to_lowercase().as_str()));
+        let header = [REMOVE_COLUMNS, MERGE_TO_COLUMNS, REPLACE_COLUMNS].map(position);
+        if std::mem::take(&mut first_line) && fields.iter().filter(|field| !field.is_empty()).all(|field| is_column(field)) {
+            columns = (header[0].unwrap_or(0), header[1].unwrap_or(1), header[2].unwrap_or(2));
+            continue;
+        }
+
+        let parse_id = |column: usize, name: &str| -> Result<u32, String> {
+            let field = fields.get(column).ok_or_else(|| format!("Line {}: missing the {} post id.", line_number, name))?;
+            field
+                .parse::<u32>()
+                .map_err(|_| format!("Line {}: {:?} is not a valid {} post id.", line_number, field, name))
+        };
+        let pair = parse_id(columns.0, "removed").and_then(|remove_post| {
+            let merge_to_post = parse_id(columns.1, "target")?;
+            let replace = match fields.get(columns.2).filter(|field| !field.is_empty()) {
+                Some(value) => Some(ReplaceContent::parse(value).ok_or_else(|| {
+                    format!("Line {}: {:?} must be keep, replace or auto.", line_number, value)
+                })?),
+                None => None,
+            };
+            if fields.len() > 3 {
+                return Err(format!("Line {}: expected at most three columns.", line_number));
+            }
+            if remove_post == merge_to_post {
+                return Err(format!("Line {}: post {} cannot be merged into itself.", line_number, remove_post));
+            }
+            Ok(MergePair { remove_post, merge_to_post, replace })
+        });
+
+        match pair {
+            Ok(pair) => match merge_pairs.iter().find(|(_, existing)| *existing == pair) {
+                Some((existing_line, _)) => {
+                    eprintln!("Line {} repeats line {}, ignoring it.", line_number, existing_line)
+                }
+                None => merge_pairs.push((line_number, pair)),
+            },
+            Err(error) => errors.push(error),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, errors.join("\n"))));
+    }
+
+    Ok(merge_pairs.into_iter().map(|(_, pair)| pair).collect())
+}
+
+/// Fetches every post in `ids`, returning the posts found and the ids the server does not have.
+pub async fn fetch_posts_by_id(
+    client: &SzurubooruClient,
+    ids: &[u32],
+) -> SzurubooruResult<(HashMap<u32, PostResource>, Vec<u32>)> {
+    let mut posts = HashMap::new();
+    let mut missing = Vec::new();
+
+    for id in ids {
+        if posts.contains_key(id) || missing.contains(id) {
+            continue;
+        }
+        match client.request().get_post(*id).await {
+            Ok(post) => {
+                posts.insert(*id, post);
+            }
+            Err(e) if is_not_found(&e) => missing.push(*id),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok((posts, missing))
+}
+
+/// Pages through every post matching `query`.
+pub async fn fetch_posts(client: &SzurubooruClient, query: &Vec<QueryToken>) -> SzurubooruResult<Vec<PostResource>> {
+    fetch_all(client, |request| async move { request.list_posts(Some(query)).await }).await
+}
+
+/// Reads post ids separated by whitespace, commas or new lines. `#` starts a comment.
+pub fn read_ids(file_path: &str) -> Result<Vec<u32>, SzurubooruClientError> {
+    let content = fs::read_to_string(file_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut ids = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        for id in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|id| !id.is_empty()) {
+            let id = id.parse::<u32>().map_err(|_| {
+                SzurubooruClientError::IOError(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Line {}: {} is not a post id.", index + 1, id),
+                ))
+            })?;
+            ids.push(id);
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Reads `post_id file` lines, the file path is everything after the first whitespace so it may
+/// contain spaces. Blank lines and lines starting with `#` are skipped.
+pub fn read_post_file_pairs(file_path: &str) -> Result<Vec<(u32, PathBuf)>, SzurubooruClientError> {
+    let content = fs::read_to_string(file_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut pairs = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |message: String| {
+            SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, format!("Line {}: {}", index + 1, message)))
+        };
+        let (id, file) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| invalid("expected a post id followed by a file.".to_string()))?;
+        let id = id.parse::<u32>().map_err(|_| invalid(format!("{} is not a post id.", id)))?;
+        pairs.push((id, PathBuf::from(file.trim())));
+    }
+
+    Ok(pairs)
+}
+
+/// File extension of the post content, taken from its URL and falling back to the mime type.
+pub fn content_extension(post: &PostResource) -> String {
+    post.content_url
+        .as_deref()
+        .and_then(|url| Path::new(url).extension())
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .or_else(|| {
+            post.mime_type
+                .as_deref()
+                .and_then(|mime| mime.split('/').nth(1))
+                .map(|subtype| subtype.replace("jpeg", "jpg"))
+        })
+        .unwrap_or_else(|| "bin".to_string())
+}
+
+/// Sidecar in the shape `make_post_with_metadata` reads, so exported files can be uploaded again.
+pub fn make_sidecar(post: &PostResource) -> Value {
+    let tags: Vec<String> = post
+        .tags
+        .iter()
+        .flatten()
+        .filter_map(|tag| tag.names.first().cloned())
+        .collect();
+
+    serde_json::json!({
+        "category": "oxibooru",
+        "id": post.id,
+        "tags": tags,
+        "source": post.source,
+        "safety": post.safety,
+        "checksum": post.checksum,
+    })
+}
+
+/// Downloads the content of `post` to `dir/file_name` with a `<file_name>.json` sidecar next to it.
+pub async fn download_with_sidecar(
+    client: &SzurubooruClient,
+    post: &PostResource,
+    dir: &Path,
+    file_name: &str,
+) -> SzurubooruResult<PathBuf> {
+    let file_path = dir.join(file_name);
+    client.request().download_image_to_path(post.id.unwrap_or_default(), &file_path).await?;
+
+    let sidecar = serde_json::to_string_pretty(&make_sidecar(post)).map_err(|e| {
+        SzurubooruClientError::ResponseParsingError(e, "Error serializing sidecar".to_string())
+    })?;
+    fs::write(dir.join(format!("{}.json", file_name)), sidecar).map_err(|e| SzurubooruClientError::IOError(e))?;
+
+    Ok(file_path)
 }
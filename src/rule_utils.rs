@@ -0,0 +1,114 @@
+// rule_utils.rs
+
+use errors::SzurubooruClientError;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use szurubooru_client::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleKind {
+    Implication,
+    Suggestion,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub line: usize,
+    pub kind: RuleKind,
+    pub antecedent: String,
+    pub consequent: String,
+}
+
+/// Reads rule lines like `cat_ears -> animal_ears` (implication) or `cat_ears ~> cat` (suggestion).
+/// Either side may list several comma separated tags, blank lines and `#` comments are skipped.
+pub fn read_rules(file_path: &str) -> SzurubooruResult<Vec<Rule>> {
+    let content = fs::read_to_string(file_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut rules = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (kind, (left, right)) = if let Some(sides) = line.split_once("->") {
+            (RuleKind::Implication, sides)
+        } else if let Some(sides) = line.split_once("~>") {
+            (RuleKind::Suggestion, sides)
+        } else {
+            return Err(SzurubooruClientError::IOError(Error::new(
+                ErrorKind::InvalidData,
+                format!("Line {} is not a rule, expected `tag -> implied` or `tag ~> suggested`.", index + 1),
+            )));
+        };
+
+        let split = |side: &str| -> Vec<String> {
+            side.split(',')
+                .map(|tag| tag.trim().to_lowercase().replace(' ', "_"))
+                .filter(|tag| !tag.is_empty())
+                .collect()
+        };
+        let (antecedents, consequents) = (split(left), split(right));
+        if antecedents.is_empty() || consequents.is_empty() {
+            return Err(SzurubooruClientError::IOError(Error::new(
+                ErrorKind::InvalidData,
+                format!("Line {} needs tags on both sides of the rule.", index + 1),
+            )));
+        }
+
+        for antecedent in &antecedents {
+            for consequent in &consequents {
+                rules.push(Rule {
+                    line: index + 1,
+                    kind,
+                    antecedent: antecedent.clone(),
+                    consequent: consequent.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(rules)
+}
+
+/// Finds an implication cycle in `graph` (tag -> implied tags), returned as the tags along the loop.
+pub fn find_cycle(graph: &HashMap<String, Vec<String>>) -> Option<Vec<String>> {
+    let mut done = HashSet::new();
+
+    for start in graph.keys() {
+        let mut path = Vec::new();
+        if let Some(cycle) = visit(start, graph, &mut path, &mut done) {
+            return Some(cycle);
+        }
+    }
+
+    None
+}
+
+fn visit(
+    tag: &String,
+    graph: &HashMap<String, Vec<String>>,
+    path: &mut Vec<String>,
+    done: &mut HashSet<String>,
+) -> Option<Vec<String>> {
+    if let Some(position) = path.iter().position(|visited| visited == tag) {
+        let mut cycle = path[position..].to_vec();
+        cycle.push(tag.clone());
+        return Some(cycle);
+    }
+    if done.contains(tag) {
+        return None;
+    }
+
+    path.push(tag.clone());
+    for next in graph.get(tag).into_iter().flatten() {
+        if let Some(cycle) = visit(next, graph, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.insert(tag.clone());
+
+    None
+}
@@ -0,0 +1,74 @@
+// snapshot_utils.rs
+
+use errors::SzurubooruClientError;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use szurubooru_client::*;
+
+/// A JSONL file in the audit directory holding what a destructive command is about to change,
+/// one `{"captured_at", "kind", "id", "data"}` record per line.
+pub struct Snapshot {
+    path: PathBuf,
+    file: fs::File,
+    records: usize,
+}
+
+#[derive(Serialize)]
+struct Record<'a, T: Serialize> {
+    captured_at: u64,
+    kind: &'a str,
+    id: String,
+    data: &'a T,
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+impl Snapshot {
+    /// Creates `<audit_dir>/<command>-<unix time>.jsonl`.
+    pub fn create(audit_dir: &str, command: &str) -> SzurubooruResult<Snapshot> {
+        fs::create_dir_all(audit_dir).map_err(|e| SzurubooruClientError::IOError(e))?;
+
+        let path = Path::new(audit_dir).join(format!("{}-{}.jsonl", command, unix_time()));
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| SzurubooruClientError::IOError(e))?;
+
+        Ok(Snapshot { path, file, records: 0 })
+    }
+
+    pub fn record<T: Serialize>(&mut self, kind: &str, id: impl ToString, data: &T) -> SzurubooruResult<()> {
+        let record = Record {
+            captured_at: unix_time(),
+            kind,
+            id: id.to_string(),
+            data,
+        };
+        let line = serde_json::to_string(&record).map_err(|e| {
+            SzurubooruClientError::ResponseParsingError(e, "Error serializing snapshot".to_string())
+        })?;
+
+        // Flushed line by line so a crash mid-run keeps everything recorded so far
+        writeln!(self.file, "{}", line).map_err(|e| SzurubooruClientError::IOError(e))?;
+        self.file.flush().map_err(|e| SzurubooruClientError::IOError(e))?;
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn records(&self) -> usize {
+        self.records
+    }
+}
@@ -0,0 +1,63 @@
+// source_utils.rs
+
+use crate::post_utils::merge_source;
+use errors::SzurubooruClientError;
+use regex::Regex;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use szurubooru_client::*;
+
+pub struct SourceRule {
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+/// Parses `regex => replacement`, the replacement may use `$1` or `${name}` capture groups.
+pub fn parse_rule(rule: &str) -> Result<SourceRule, String> {
+    let (pattern, replacement) = rule
+        .split_once("=>")
+        .ok_or_else(|| "expected `regex => replacement`".to_string())?;
+    let pattern = Regex::new(pattern.trim()).map_err(|e| e.to_string())?;
+
+    Ok(SourceRule {
+        pattern,
+        replacement: replacement.trim().to_string(),
+    })
+}
+
+/// Reads one rule per line, blank lines and `#` comments are skipped.
+pub fn read_rules(file_path: &str) -> SzurubooruResult<Vec<SourceRule>> {
+    let content = fs::read_to_string(file_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut rules = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let rule = parse_rule(line).map_err(|e| {
+            SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, format!("Line {}: {}", index + 1, e)))
+        })?;
+        rules.push(rule);
+    }
+
+    Ok(rules)
+}
+
+/// Runs every rule over each source line in order. Lines rewritten to nothing are dropped
+/// and the result is deduplicated like merged sources.
+pub fn rewrite_source(source: &str, rules: &[SourceRule]) -> Option<String> {
+    let lines: Vec<String> = source
+        .lines()
+        .map(|line| {
+            rules.iter().fold(line.to_string(), |line, rule| {
+                rule.pattern.replace_all(&line, rule.replacement.as_str()).into_owned()
+            })
+        })
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    merge_source(Some(lines.join("\n")), None)
+}
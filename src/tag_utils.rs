@@ -1,10 +1,469 @@
-use serde::Deserialize;
+// tag_utils.rs
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::csv_utils::{escape_csv_field, parse_csv_line};
+use crate::error_utils::is_not_found;
+use crate::page_utils::fetch_all;
+use crate::snapshot_utils::Snapshot;
+use crate::SettingsConfig;
+use errors::SzurubooruClientError;
+use models::{CreateUpdateTag, MergeTags, MicroTagResource, TagResource};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Error, ErrorKind};
+use std::path::Path;
+use szurubooru_client::tokens::QueryToken;
+use szurubooru_client::*;
+use tokio::time::{sleep, Duration};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Tag {
     pub name: String,
     pub category: String,
+    #[serde(default)]
     pub aliases: Vec<String>,
+    #[serde(default)]
     pub implications: Vec<String>,
+    #[serde(default)]
     pub suggested: Vec<String>,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub usages: u32,
+}
+
+impl Tag {
+    /// Builds a `Tag` from a server resource. The first name is the tag name, the rest are aliases.
+    pub fn from_resource(resource: TagResource) -> Option<Tag> {
+        let mut names = resource.names?.into_iter();
+        let name = names.next()?;
+
+        Some(Tag {
+            name,
+            category: resource.category.unwrap_or_default(),
+            aliases: names.collect(),
+            implications: micro_tag_names(resource.implications),
+            suggested: micro_tag_names(resource.suggestions),
+            usages: resource.usages.unwrap_or(0),
+        })
+    }
+}
+
+/// Wrapper so TOML files get a `[[tags]]` array at the top level.
+#[derive(Debug, Deserialize, Serialize)]
+struct TagFile {
+    tags: Vec<Tag>,
+}
+
+fn micro_tag_names(tags: Option<Vec<MicroTagResource>>) -> Vec<String> {
+    tags.unwrap_or_default()
+        .into_iter()
+        .filter_map(|tag| tag.names.first().cloned())
+        .collect()
+}
+
+/// Pages through every tag matching `query`.
+pub async fn fetch_tags(
+    client: &SzurubooruClient,
+    query: &Vec<QueryToken>,
+) -> SzurubooruResult<Vec<TagResource>> {
+    fetch_all(client, |request| async move { request.list_tags(Some(query)).await }).await
+}
+
+/// Fetches a single tag by name or alias, `None` if the server does not know it.
+pub async fn fetch_tag(client: &SzurubooruClient, name: &str) -> SzurubooruResult<Option<(Tag, u32)>> {
+    match client.request().get_tag(name).await {
+        Ok(resource) => {
+            let version = resource.version;
+            Ok(Tag::from_resource(resource).map(|tag| (tag, version)))
+        }
+        Err(e) if is_not_found(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn file_extension(file_path: &str) -> String {
+    Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Reads tag definitions from a `.json` array or a `.toml` file with a `[[tags]]` array.
+pub fn read_tags(file_path: &str) -> SzurubooruResult<Vec<Tag>> {
+    let content = fs::read_to_string(file_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+
+    match file_extension(file_path).as_str() {
+        "json" => serde_json::from_str(&content).map_err(|e| {
+            SzurubooruClientError::ResponseParsingError(e, "Error parsing tag file".to_string())
+        }),
+        "toml" => toml::from_str::<TagFile>(&content)
+            .map(|file| file.tags)
+            .map_err(|e| SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, e.to_string()))),
+        _ => Err(SzurubooruClientError::IOError(Error::new(
+            ErrorKind::InvalidInput,
+            "Tag files must be .json or .toml",
+        ))),
+    }
+}
+
+/// Reads just the tag names from a file written by `write_tags`.
+pub fn read_tag_names(file_path: &str) -> SzurubooruResult<Vec<String>> {
+    match file_extension(file_path).as_str() {
+        "json" | "toml" => Ok(read_tags(file_path)?.into_iter().map(|tag| tag.name).collect()),
+        extension => {
+            let content = fs::read_to_string(file_path).map_err(|e| SzurubooruClientError::IOError(e))?;
+            let lines = content.lines().skip(if extension == "csv" { 1 } else { 0 });
+            Ok(lines
+                .map(|line| match extension {
+                    "csv" => parse_csv_line(line, ',').swap_remove(0),
+                    _ => line.trim().to_string(),
+                })
+                .filter(|name| !name.is_empty())
+                .collect())
+        }
+    }
+}
+
+/// Writes tags to `file_path`, picking the format from the extension:
+/// `.json`, `.toml` and `.csv` keep the full `Tag` shape, anything else gets one name per line.
+pub fn write_tags(file_path: &str, tags: &[Tag]) -> SzurubooruResult<()> {
+    let content = match file_extension(file_path).as_str() {
+        "json" => serde_json::to_string_pretty(tags).map_err(|e| {
+            SzurubooruClientError::ResponseParsingError(e, "Error serializing tags".to_string())
+        })?,
+        "toml" => toml::to_string(&TagFile { tags: tags.to_vec() })
+            .map_err(|e| SzurubooruClientError::IOError(Error::new(ErrorKind::InvalidData, e.to_string())))?,
+        "csv" => {
+            let mut content = String::from("name,category,usages,aliases,implications,suggested\n");
+            for tag in tags {
+                content.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    escape_csv_field(&tag.name),
+                    escape_csv_field(&tag.category),
+                    tag.usages,
+                    escape_csv_field(&tag.aliases.join(" ")),
+                    escape_csv_field(&tag.implications.join(" ")),
+                    escape_csv_field(&tag.suggested.join(" ")),
+                ));
+            }
+            content
+        }
+        _ => tags
+            .iter()
+            .map(|tag| format!("{}\n", tag.name))
+            .collect(),
+    };
+
+    fs::write(file_path, content).map_err(|e| SzurubooruClientError::IOError(e))
+}
+
+/// Difference between a tag on the server and the wanted definition.
+/// Imports are additive: names, implications and suggestions are only ever added.
+#[derive(Debug)]
+pub struct TagDiff {
+    pub current: Option<(Tag, u32)>,
+    pub target: Tag,
+}
+
+/// Returns `None` when the server already matches `wanted`.
+pub fn diff_tag(current: Option<(Tag, u32)>, wanted: &Tag) -> Option<TagDiff> {
+    let target = match &current {
+        Some((tag, _)) => {
+            let mut target = tag.clone();
+            if !wanted.category.is_empty() {
+                target.category = wanted.category.clone();
+            }
+            // `wanted.name` may be an alias of the current tag
+            let wanted_names = std::iter::once(&wanted.name).chain(wanted.aliases.iter());
+            extend_unique(&mut target.aliases, wanted_names.filter(|name| **name != target.name));
+            extend_unique(&mut target.implications, wanted.implications.iter());
+            extend_unique(&mut target.suggested, wanted.suggested.iter());
+            target
+        }
+        None => wanted.clone(),
+    };
+
+    match &current {
+        Some((tag, _))
+            if tag.category == target.category
+                && tag.aliases == target.aliases
+                && tag.implications == target.implications
+                && tag.suggested == target.suggested =>
+        {
+            None
+        }
+        _ => Some(TagDiff { current, target }),
+    }
+}
+
+fn extend_unique<'a>(list: &mut Vec<String>, items: impl Iterator<Item = &'a String>) {
+    for item in items {
+        if !list.contains(item) {
+            list.push(item.clone());
+        }
+    }
+}
+
+fn added<'a>(before: &'a [String], after: &'a [String]) -> Vec<&'a str> {
+    after
+        .iter()
+        .filter(|item| !before.contains(item))
+        .map(String::as_str)
+        .collect()
+}
+
+impl fmt::Display for TagDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let target = &self.target;
+        let Some((current, _)) = &self.current else {
+            write!(f, "+ {} [{}]", target.name, target.category)?;
+            for (label, items) in [
+                ("aliases", &target.aliases),
+                ("implications", &target.implications),
+                ("suggested", &target.suggested),
+            ] {
+                if !items.is_empty() {
+                    write!(f, "\n    {}: {}", label, items.join(" "))?;
+                }
+            }
+            return Ok(());
+        };
+
+        write!(f, "~ {}", target.name)?;
+        if current.category != target.category {
+            write!(f, "\n    category: {} -> {}", current.category, target.category)?;
+        }
+        for (label, before, after) in [
+            ("aliases", &current.aliases, &target.aliases),
+            ("implications", &current.implications, &target.implications),
+            ("suggested", &current.suggested, &target.suggested),
+        ] {
+            let added = added(before, after);
+            if !added.is_empty() {
+                write!(f, "\n    {}: +{}", label, added.join(" +"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates or updates the tag described by `diff`.
+pub async fn apply_tag_diff(client: &SzurubooruClient, diff: &TagDiff) -> SzurubooruResult<()> {
+    let target = &diff.target;
+    let mut names = vec![target.name.clone()];
+    names.extend(target.aliases.iter().cloned());
+
+    let mut tag = CreateUpdateTag {
+        version: None,
+        names: Some(names),
+        category: if target.category.is_empty() { None } else { Some(target.category.clone()) },
+        description: None,
+        implications: Some(target.implications.clone()),
+        suggestions: Some(target.suggested.clone()),
+    };
+
+    match &diff.current {
+        Some((current, version)) => {
+            tag.version = Some(*version);
+            client.request().update_tag(&current.name, &tag).await?;
+        }
+        None => {
+            client.request().create_tag(&tag).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct ApplySummary {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+}
+
+impl fmt::Display for ApplySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} created, {} updated, {} unchanged, {} failed",
+            self.created, self.updated, self.unchanged, self.failed
+        )
+    }
+}
+
+/// Diffs every tag against the server and applies the changes, or only prints them on a dry run.
+/// Tags are fetched right before they are applied so earlier changes in the same run are seen.
+pub async fn apply_tags(
+    client: &SzurubooruClient,
+    tags: &[Tag],
+    dry_run: bool,
+    settings: &SettingsConfig,
+) -> SzurubooruResult<ApplySummary> {
+    let mut summary = ApplySummary::default();
+
+    for tag in tags {
+        let result = async {
+            let current = fetch_tag(client, &tag.name).await?;
+            let Some(diff) = diff_tag(current, tag) else {
+                return Ok(None);
+            };
+            println!("{}", diff);
+
+            if !dry_run {
+                apply_tag_diff(client, &diff).await?;
+                sleep(Duration::from_millis(settings.timeout)).await;
+            }
+            Ok::<_, SzurubooruClientError>(Some(diff.current.is_none()))
+        };
+
+        match result.await {
+            Ok(None) => summary.unchanged += 1,
+            Ok(Some(true)) => summary.created += 1,
+            Ok(Some(false)) => summary.updated += 1,
+            Err(e) => {
+                eprintln!("Error applying tag {}: {}", tag.name, e);
+                summary.failed += 1;
+                if !settings.skip_on_error {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Merges `remove_tag` into `merge_to_tag`, optionally keeping the removed name as an alias.
+/// Returns how many posts carried the removed tag.
+pub async fn merge_tag_pair(
+    client: &SzurubooruClient,
+    remove_tag: &str,
+    merge_to_tag: &str,
+    add_alias: bool,
+) -> SzurubooruResult<u32> {
+    let missing = |name: &str| {
+        SzurubooruClientError::IOError(Error::new(ErrorKind::NotFound, format!("Tag {} does not exist.", name)))
+    };
+    let (removed, remove_tag_version) = fetch_tag(client, remove_tag).await?.ok_or_else(|| missing(remove_tag))?;
+    let (target, merge_to_version) = fetch_tag(client, merge_to_tag).await?.ok_or_else(|| missing(merge_to_tag))?;
+
+    let merge = MergeTags {
+        remove_tag_version,
+        remove_tag: removed.name.clone(),
+        merge_to_version,
+        merge_to_tag: target.name.clone(),
+    };
+    let merged = client.request().merge_tags(&merge).await?;
+
+    if add_alias {
+        let mut names = merged.names.clone().unwrap_or_else(|| vec![target.name.clone()]);
+        // The server discards the aliases of the removed tag, keep them alongside its name
+        for name in std::iter::once(&removed.name).chain(removed.aliases.iter()) {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        let update = CreateUpdateTag {
+            version: Some(merged.version),
+            names: Some(names),
+            category: None,
+            description: None,
+            implications: None,
+            suggestions: None,
+        };
+        client.request().update_tag(&target.name, &update).await?;
+    }
+
+    Ok(removed.usages)
+}
+
+/// Renames `old_name` to `new_name`, keeping the old name as an alias when `keep_alias` is set.
+/// If `new_name` already belongs to another tag the two tags are recorded in `snapshot` and merged
+/// instead. On a dry run only the planned change is printed.
+pub async fn rename_tag(
+    client: &SzurubooruClient,
+    old_name: &str,
+    new_name: &str,
+    keep_alias: bool,
+    dry_run: bool,
+    snapshot: Option<&mut Snapshot>,
+) -> SzurubooruResult<()> {
+    let (old_tag, version) = fetch_tag(client, old_name).await?.ok_or_else(|| {
+        SzurubooruClientError::IOError(Error::new(ErrorKind::NotFound, format!("Tag {} does not exist.", old_name)))
+    })?;
+
+    if let Some((new_tag, _)) = fetch_tag(client, new_name).await? {
+        if new_tag.name != old_tag.name {
+            println!("= {} -> {} (merge, {} posts)", old_tag.name, new_tag.name, old_tag.usages);
+            if !dry_run {
+                if let Some(snapshot) = snapshot {
+                    snapshot.record("tag", &old_tag.name, &old_tag)?;
+                    snapshot.record("tag", &new_tag.name, &new_tag)?;
+                }
+                merge_tag_pair(client, &old_tag.name, &new_tag.name, keep_alias).await?;
+            }
+            return Ok(());
+        }
+    }
+
+    let mut names = vec![new_name.to_string()];
+    names.extend(
+        old_tag
+            .aliases
+            .iter()
+            .filter(|alias| !alias.eq_ignore_ascii_case(new_name))
+            .cloned(),
+    );
+    if keep_alias && !old_tag.name.eq_ignore_ascii_case(new_name) {
+        names.push(old_tag.name.clone());
+    }
+
+    println!("~ {} -> {} (names: {})", old_tag.name, new_name, names.join(" "));
+    if !dry_run {
+        let update = CreateUpdateTag {
+            version: Some(version),
+            names: Some(names),
+            category: None,
+            description: None,
+            implications: None,
+            suggestions: None,
+        };
+        client.request().update_tag(&old_tag.name, &update).await?;
+    }
+
+    Ok(())
+}
+
+/// Reads `remove_tag merge_to_tag` pairs, one per line. Blank lines and `#` comments are skipped.
+pub fn read_name_pairs(file_path: &str) -> Result<Vec<(String, String)>, SzurubooruClientError> {
+    let path = Path::new(file_path);
+    if !path.is_file() {
+        let dir_error: std::io::Error = Error::new(std::io::ErrorKind::Other, "Provided path is not a file");
+        return Err(SzurubooruClientError::IOError(dir_error));
+    }
+
+    let file = fs::File::open(path).map_err(|e| SzurubooruClientError::IOError(e))?;
+    let mut name_pairs = Vec::new();
+
+    for (index, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| SzurubooruClientError::IOError(e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_whitespace().collect::<Vec<&str>>()[..] {
+            [first, second] => name_pairs.push((first.to_string(), second.to_string())),
+            _ => {
+                return Err(SzurubooruClientError::IOError(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Line {} must contain exactly two tag names.", index + 1),
+                )))
+            }
+        }
+    }
+
+    Ok(name_pairs)
+}